@@ -4,7 +4,7 @@ use chrono::{Datelike, NaiveDateTime, NaiveTime, Timelike};
 use color_eyre::{eyre, Result};
 
 use crate::{daylio, Daylio, merge, NUMBER_OF_PREDEFINED_MOODS};
-use crate::parse_pdf::{DayEntry, ParsedPdf, StatLine};
+use crate::parse_pdf::{ParsedDayEntry, ParsedPdf, StatLine};
 
 #[derive(Debug, PartialEq, Clone, Default)]
 struct ProcessedDayEntry {
@@ -42,31 +42,27 @@ fn convert_24_hour_to_12_hour(time_str: &str) -> Result<String> {
         eyre::bail!("Invalid date format: {}", time_str);
     }
 
-    let mut hour = date_parts[0].to_owned();
+    // the hour is reformatted, as some exports pad it with a zero
+    let hour = date_parts[0].parse::<u8>()?;
     let minute = date_parts[1];
 
-    let am_pm = if date_parts.len() == 3 {
-        date_parts[2]
+    let (hour, am_pm) = if date_parts.len() == 3 {
+        // some 12h exports write midnight as 00
+        (if hour == 0 { 12 } else { hour }, date_parts[2])
     } else {
-        // 24h clock
-        let hour_int = hour.parse::<u8>().unwrap();
-        if hour_int > 12 {
-            hour = (hour_int - 12).to_string();
-            "pm"
-        } else {
-            "am"
+        // 24h clock: 0 is midnight and 12 is noon
+        match hour {
+            0 => (12, "am"),
+            1..=11 => (hour, "am"),
+            12 => (12, "pm"),
+            _ => (hour - 12, "pm"),
         }
     };
 
-    // sanitize hour
-    if hour == "00" {
-        hour = "12".to_owned();
-    }
-
     Ok(format!("{hour} {minute} {am_pm}"))
 }
 
-fn parse_date(entry: &DayEntry) -> Result<NaiveDateTime> {
+pub(crate) fn parse_date(entry: &ParsedDayEntry) -> Result<NaiveDateTime> {
     // skip the day of the week
     let mut time_str = entry
         .day_hour
@@ -84,7 +80,7 @@ fn parse_date(entry: &DayEntry) -> Result<NaiveDateTime> {
 }
 
 /// Extracts tags from the note, and returns the note with the tags removed.
-fn extract_tags(entry: &DayEntry, stats: &Vec<StatLine>) -> (String, Vec<String>) {
+fn extract_tags(entry: &ParsedDayEntry, stats: &Vec<StatLine>) -> (String, Vec<String>) {
     let mut entry_tags = Vec::new();
 
     let mut last_tag_line = None;
@@ -112,7 +108,7 @@ fn extract_tags(entry: &DayEntry, stats: &Vec<StatLine>) -> (String, Vec<String>
     (note.join("\n"), entry_tags)
 }
 
-fn predefined_mood_idx(custom_name: &str) -> Option<i64> {
+pub(crate) fn predefined_mood_idx(custom_name: &str) -> Option<i64> {
     match custom_name.to_lowercase().as_ref() {
         "super" | "rad" => Some(1),
         "bien" | "good" => Some(2),
@@ -168,15 +164,17 @@ fn list_tags_and_moods(parsed: &ParsedPdf) -> (Vec<Tag>, Vec<Mood>) {
     (tags.into_iter().collect(), moods)
 }
 
-impl From<ParsedPdf> for ProcessedPdf {
-    fn from(parsed: ParsedPdf) -> Self {
+impl TryFrom<ParsedPdf> for ProcessedPdf {
+    type Error = eyre::Report;
+
+    fn try_from(parsed: ParsedPdf) -> Result<Self> {
         let (tags, moods) = list_tags_and_moods(&parsed);
 
         let day_entries = parsed
             .day_entries
             .into_iter()
-            .map(|entry| {
-                let date = parse_date(&entry).unwrap();
+            .map(|entry| -> Result<ProcessedDayEntry> {
+                let date = parse_date(&entry)?;
                 let (note, entry_tags) = extract_tags(&entry, &parsed.stats);
 
                 let entry_mood = moods.iter().find(|x| x.name == entry.mood).unwrap().id;
@@ -185,20 +183,20 @@ impl From<ParsedPdf> for ProcessedPdf {
                     .map(|x| tags.iter().find(|y| y.name == *x).unwrap().id)
                     .collect();
 
-                ProcessedDayEntry {
+                Ok(ProcessedDayEntry {
                     date,
                     mood: entry_mood,
                     tags: entry_tags,
                     note,
-                }
+                })
             })
-            .collect();
+            .collect::<Result<_>>()?;
 
-        ProcessedPdf {
+        Ok(ProcessedPdf {
             day_entries,
             moods,
             tags,
-        }
+        })
     }
 }
 
@@ -225,6 +223,8 @@ impl From<Tag> for daylio::Tag {
             id: tag.id,
             name: tag.name,
             icon: 1,
+            // the PDF has no groups, so use the default one, created by `Daylio::default`
+            id_tag_group: 1,
             ..Default::default()
         }
     }
@@ -269,7 +269,7 @@ mod tests {
 
     #[test]
     fn test_parse_date() {
-        let entry = DayEntry {
+        let entry = ParsedDayEntry {
             date: NaiveDate::from_ymd_opt(2022, 8, 2).unwrap(),
             day_hour: "Monday 8 45 PM".to_owned(),
             mood: String::new(),
@@ -284,6 +284,50 @@ mod tests {
         assert_eq!(date.second(), 0);
     }
 
+    fn parse_time(day_hour: &str) -> (u32, u32) {
+        let entry = ParsedDayEntry {
+            date: NaiveDate::from_ymd_opt(2022, 8, 2).unwrap(),
+            day_hour: day_hour.to_owned(),
+            mood: String::new(),
+            note: vec![],
+        };
+        let date = parse_date(&entry).unwrap();
+        (date.hour(), date.minute())
+    }
+
+    #[test]
+    fn test_parse_date_midnight_and_noon() {
+        assert_eq!(parse_time("Monday 12 00 AM"), (0, 0));
+        assert_eq!(parse_time("Monday 00 15 AM"), (0, 15));
+        assert_eq!(parse_time("Monday 12 30 PM"), (12, 30));
+        assert_eq!(parse_time("Monday 00 00"), (0, 0));
+        assert_eq!(parse_time("Monday 0:05"), (0, 5));
+        assert_eq!(parse_time("Monday 12:00"), (12, 0));
+        assert_eq!(parse_time("Monday 23 59"), (23, 59));
+    }
+
+    #[test]
+    fn test_convert_24_hour_to_12_hour() {
+        let convert = |time: &str| convert_24_hour_to_12_hour(time).unwrap();
+
+        assert_eq!(convert("00 00"), "12 00 am");
+        assert_eq!(convert("0 15"), "12 15 am");
+        assert_eq!(convert("12 00"), "12 00 pm");
+        assert_eq!(convert("12 30"), "12 30 pm");
+        assert_eq!(convert("13 05"), "1 05 pm");
+        assert_eq!(convert("23 59"), "11 59 pm");
+
+        assert_eq!(parse_time("Monday 13:05"), (13, 5));
+    }
+
+    #[test]
+    fn test_parse_date_zero_padded_hour() {
+        assert_eq!(parse_time("Monday 08 45 pm"), (20, 45));
+        assert_eq!(parse_time("Monday 8 45 pm"), (20, 45));
+        assert_eq!(parse_time("Monday 08 45 AM"), (8, 45));
+        assert_eq!(parse_time("Monday 08:45"), (8, 45));
+    }
+
     impl StatLine {
         fn with_name(name: &str) -> Self {
             StatLine {
@@ -295,7 +339,7 @@ mod tests {
 
     #[test]
     fn test_extract_tags() {
-        let entry = DayEntry {
+        let entry = ParsedDayEntry {
             date: NaiveDate::from_ymd_opt(2022, 9, 2).unwrap(),
             day_hour: String::new(),
             mood: String::new(),
@@ -338,19 +382,19 @@ mod tests {
     fn test_processed_pdf_from_parsed_pdf() {
         let parsed = ParsedPdf {
             day_entries: vec![
-                DayEntry {
+                ParsedDayEntry {
                     date: NaiveDate::from_ymd_opt(2022, 9, 2).unwrap(),
                     day_hour: "Monday 8 45 PM".to_owned(),
                     mood: "rad".to_owned(),
                     note: vec!["This is a note".to_owned()],
                 },
-                DayEntry {
+                ParsedDayEntry {
                     date: NaiveDate::from_ymd_opt(2022, 9, 3).unwrap(),
                     day_hour: "Tuesday 8 45 AM".to_owned(),
                     mood: "rad".to_owned(),
                     note: vec!["This is a note²".to_owned()],
                 },
-                DayEntry {
+                ParsedDayEntry {
                     date: NaiveDate::from_ymd_opt(2022, 9, 3).unwrap(),
                     day_hour: "Tuesday 9 00 AM".to_owned(),
                     mood: "good".to_owned(),
@@ -369,6 +413,7 @@ mod tests {
                 StatLine::with_name("yet another tag"),
                 StatLine::with_name("Tag that won't be matched"),
             ],
+            warnings: vec![],
         };
 
         let expected = ProcessedPdf {
@@ -422,8 +467,24 @@ mod tests {
             ],
         };
 
-        let processed = ProcessedPdf::from(parsed);
+        let processed = ProcessedPdf::try_from(parsed).unwrap();
 
         assert_eq!(processed, expected);
     }
+
+    #[test]
+    fn test_processed_pdf_invalid_time() {
+        let parsed = ParsedPdf {
+            day_entries: vec![ParsedDayEntry {
+                date: NaiveDate::from_ymd_opt(2022, 9, 2).unwrap(),
+                day_hour: "Someday at noon".to_owned(),
+                mood: "rad".to_owned(),
+                note: vec![],
+            }],
+            stats: vec![StatLine::with_name("rad")],
+            warnings: vec![],
+        };
+
+        assert!(ProcessedPdf::try_from(parsed).is_err());
+    }
 }
@@ -0,0 +1,369 @@
+//! This module reads and writes the CSV export of Daylio
+
+use std::fmt::Write;
+use std::mem;
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use color_eyre::eyre::{ContextCompat, eyre, WrapErr};
+use color_eyre::Result;
+
+use crate::{CustomMood, DayEntry, Daylio, merge, Tag};
+use crate::analyze_pdf::predefined_mood_idx;
+
+const HEADER: [&str; 8] = [
+    "full_date",
+    "date",
+    "weekday",
+    "time",
+    "mood",
+    "activities",
+    "note_title",
+    "note",
+];
+
+/// How line breaks in notes are written, as some spreadsheet tools mishandle them in cells
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CsvNewlines {
+    /// Quote the field and keep the line breaks, as described in RFC 4180
+    #[default]
+    Quote,
+    /// Replace line breaks with a literal `\n`
+    Escape,
+    /// Replace line breaks with a space
+    Space,
+}
+
+/// Settings of the CSV format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsvOptions {
+    /// Separates the fields. Spreadsheets in many European locales expect `;`
+    pub delimiter: u8,
+    /// Only used when writing: replaced line breaks cannot be told apart from the note text
+    pub newlines: CsvNewlines,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            newlines: CsvNewlines::default(),
+        }
+    }
+}
+
+/// Splits RFC 4180 CSV into records, with fields separated by `delimiter`.
+/// Quoted fields can contain delimiters, line breaks and quotes, which are doubled.
+/// Line breaks are normalized to `\n`
+fn parse_records(input: &str, delimiter: char) -> Result<Vec<Vec<String>>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                '\r' if chars.peek() == Some(&'\n') => {}
+                _ => field.push(c),
+            }
+        } else {
+            match c {
+                '"' => in_quotes = true,
+                c if c == delimiter => record.push(mem::take(&mut field)),
+                '\r' if chars.peek() == Some(&'\n') => {}
+                '\n' => {
+                    record.push(mem::take(&mut field));
+                    records.push(mem::take(&mut record));
+                }
+                _ => field.push(c),
+            }
+        }
+    }
+
+    if in_quotes {
+        return Err(eyre!("Unterminated quoted field"));
+    }
+
+    // the last line may not end with a line break
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+/// Replaces line breaks if asked, then quotes the field if needed, as described in RFC 4180
+fn escape_field(field: &str, options: CsvOptions) -> String {
+    let field = match options.newlines {
+        CsvNewlines::Quote => field.to_owned(),
+        CsvNewlines::Escape => field.replace("\r\n", "\\n").replace(['\n', '\r'], "\\n"),
+        CsvNewlines::Space => field.replace("\r\n", " ").replace(['\n', '\r'], " "),
+    };
+
+    if field.contains([char::from(options.delimiter), '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+/// The time follows the phone settings, so it can use a 12h or 24h clock
+fn parse_time(time: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(time, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(time, "%I:%M %p"))
+        .wrap_err_with(|| format!("Invalid time: {time}"))
+}
+
+/// Activities are separated by pipes, as written by [`write_csv`].
+/// Tag names can contain commas, so they are not separators
+fn split_activities(activities: &str) -> impl Iterator<Item = &str> {
+    activities
+        .split('|')
+        .map(str::trim)
+        .filter(|activity| !activity.is_empty())
+}
+
+pub(crate) fn parse_csv(input: &str, options: CsvOptions) -> Result<Daylio> {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+
+    let mut records = parse_records(input, char::from(options.delimiter))?
+        .into_iter()
+        .filter(|record| record.iter().any(|field| !field.is_empty()));
+
+    let header = records.next().wrap_err("Empty CSV")?;
+    let column = |name: &str| header.iter().position(|column| column.trim() == name);
+    let required = |name: &str| column(name).wrap_err_with(|| format!("Missing column: {name}"));
+
+    let full_date = required("full_date")?;
+    let time = required("time")?;
+    let mood = required("mood")?;
+    let activities = column("activities");
+    let note_title = column("note_title");
+    let note = column("note");
+
+    // predefined moods are always there, custom ones are added as they are found
+    let mut custom_moods = Daylio::default().custom_moods;
+    let mut tags: Vec<Tag> = Vec::new();
+    let mut day_entries = Vec::new();
+
+    for (i, record) in records.enumerate() {
+        let field =
+            |index: Option<usize>| index.and_then(|i| record.get(i)).map_or("", String::as_str);
+
+        let date = NaiveDate::parse_from_str(field(Some(full_date)), "%Y-%m-%d")
+            .wrap_err_with(|| format!("Invalid date in entry {}", i + 1))?;
+        let date = NaiveDateTime::new(date, parse_time(field(Some(time)))?);
+
+        let mood_name = field(Some(mood));
+        let mood_id = match predefined_mood_idx(mood_name) {
+            Some(id) => id,
+            None => {
+                let existing = custom_moods
+                    .iter()
+                    .find(|mood| mood.custom_name == mood_name);
+                if let Some(mood) = existing {
+                    mood.id
+                } else {
+                    let id = custom_moods.len() as i64 + 1;
+                    custom_moods.push(CustomMood {
+                        id,
+                        custom_name: mood_name.to_owned(),
+                        // the CSV only has the mood name, so the group of custom moods is lost.
+                        // The middle group is the least wrong guess
+                        mood_group_id: 3,
+                        icon_id: 1,
+                        predefined_name_id: -1,
+                        ..Default::default()
+                    });
+                    id
+                }
+            }
+        };
+
+        let mut entry_tags = Vec::new();
+        for name in split_activities(field(activities)) {
+            let id = if let Some(tag) = tags.iter().find(|tag| tag.name == name) {
+                tag.id
+            } else {
+                let id = tags.len() as i64 + 1;
+                tags.push(Tag {
+                    id,
+                    name: name.to_owned(),
+                    icon: 1,
+                    id_tag_group: 1,
+                    ..Default::default()
+                });
+                id
+            };
+            entry_tags.push(id);
+        }
+
+        day_entries.push(DayEntry {
+            id: i as i64 + 1,
+            minute: i64::from(date.minute()),
+            hour: i64::from(date.hour()),
+            day: i64::from(date.day()),
+            month: i64::from(date.month()) - 1, // month is 0-indexed in Daylio
+            year: i64::from(date.year()),
+            datetime: date.and_utc().timestamp_millis(),
+            mood: mood_id,
+            note: field(note).to_owned(),
+            note_title: field(note_title).to_owned(),
+            tags: entry_tags,
+            ..Default::default()
+        });
+    }
+
+    // merging with the default backup sanitizes ids and orders, as for PDF imports
+    Ok(merge(
+        Daylio::default(),
+        Daylio {
+            custom_moods,
+            tags,
+            day_entries,
+            ..Default::default()
+        },
+    ))
+}
+
+/// Writes the day entries with the same columns as the official export
+pub(crate) fn write_csv(daylio: &Daylio, options: CsvOptions) -> Result<String> {
+    let delimiter = char::from(options.delimiter).to_string();
+    let mut csv = format!("{}\n", HEADER.join(&delimiter));
+
+    for entry in &daylio.day_entries {
+        let date = entry
+            .date()
+            .wrap_err_with(|| format!("Invalid date in entry {}", entry.id))?;
+        let time =
+            NaiveTime::from_hms_opt(u32::try_from(entry.hour)?, u32::try_from(entry.minute)?, 0)
+                .wrap_err_with(|| format!("Invalid time in entry {}", entry.id))?;
+
+        let mood = daylio
+            .custom_moods
+            .iter()
+            .find(|mood| mood.id == entry.mood)
+            .wrap_err_with(|| format!("Unknown mood {} in entry {}", entry.mood, entry.id))?
+            .display_name();
+
+        let activities = entry
+            .tags
+            .iter()
+            .filter_map(|id| daylio.tags.iter().find(|tag| tag.id == *id))
+            .map(|tag| tag.name.as_str())
+            .collect::<Vec<_>>()
+            .join(" | ");
+
+        let fields = [
+            date.format("%Y-%m-%d").to_string(),
+            date.format("%B %-d").to_string(),
+            date.format("%A").to_string(),
+            time.format("%H:%M").to_string(),
+            mood,
+            activities,
+            entry.note_title.clone(),
+            entry.note.clone(),
+        ]
+        .map(|field| escape_field(&field, options));
+        writeln!(csv, "{}", fields.join(&delimiter))?;
+    }
+
+    Ok(csv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_records() {
+        let input = "a,b,c\r\n\"with, comma\",\"with \"\"quotes\"\"\",\"multi\r\nline\"\nlast,,";
+
+        let records = parse_records(input, ',').unwrap();
+
+        assert_eq!(
+            records,
+            vec![
+                vec!["a", "b", "c"],
+                vec!["with, comma", "with \"quotes\"", "multi\nline"],
+                vec!["last", "", ""],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_records_unterminated() {
+        assert!(parse_records("a,\"b\nc", ',').is_err());
+    }
+
+    #[test]
+    fn test_escape_field() {
+        let options = CsvOptions::default();
+        assert_eq!(escape_field("plain", options), "plain");
+        assert_eq!(escape_field("a, b", options), "\"a, b\"");
+        assert_eq!(escape_field("say \"hi\"", options), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape_field("two\nlines", options), "\"two\nlines\"");
+
+        let fields = ["plain", "a, b", "say \"hi\"", "two\nlines"];
+        let line = fields.map(|field| escape_field(field, options)).join(",");
+        assert_eq!(parse_records(&line, ',').unwrap(), vec![fields.to_vec()]);
+    }
+
+    #[test]
+    fn test_escape_field_semicolon() {
+        let options = CsvOptions {
+            delimiter: b';',
+            ..Default::default()
+        };
+        assert_eq!(escape_field("a, b", options), "a, b");
+        assert_eq!(escape_field("a; b", options), "\"a; b\"");
+
+        let fields = ["a, b", "a; b"];
+        let line = fields.map(|field| escape_field(field, options)).join(";");
+        assert_eq!(parse_records(&line, ';').unwrap(), vec![fields.to_vec()]);
+    }
+
+    #[test]
+    fn test_escape_field_newlines() {
+        let with = |newlines| CsvOptions {
+            newlines,
+            ..Default::default()
+        };
+
+        let note = "two\nlines\r\nthree";
+        assert_eq!(
+            escape_field(note, with(CsvNewlines::Quote)),
+            "\"two\nlines\r\nthree\""
+        );
+        assert_eq!(
+            escape_field(note, with(CsvNewlines::Escape)),
+            "two\\nlines\\nthree"
+        );
+        assert_eq!(
+            escape_field(note, with(CsvNewlines::Space)),
+            "two lines three"
+        );
+    }
+
+    #[test]
+    fn test_parse_time() {
+        let expected = NaiveTime::from_hms_opt(20, 5, 0).unwrap();
+
+        assert_eq!(parse_time("20:05").unwrap(), expected);
+        assert_eq!(parse_time("8:05 PM").unwrap(), expected);
+        assert!(parse_time("evening").is_err());
+    }
+
+    #[test]
+    fn test_split_activities() {
+        let activities = split_activities("work | sport|  family, friends ").collect::<Vec<_>>();
+        assert_eq!(activities, ["work", "sport", "family, friends"]);
+    }
+}
@@ -1,11 +1,29 @@
 use core::default::Default;
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
 
+use chrono::{DateTime, NaiveDate, Utc};
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
 use serde_derive::Deserialize;
 use serde_derive::Serialize;
 use serde_json::Value;
 
 pub const NUMBER_OF_PREDEFINED_MOODS: i64 = 5;
 
+/// Name of a predefined mood in the English version of the app, from its `predefined_name_id`
+#[must_use]
+pub fn predefined_mood_name_en(id: i64) -> Option<&'static str> {
+    match id {
+        1 => Some("rad"),
+        2 => Some("good"),
+        3 => Some("meh"),
+        4 => Some("bad"),
+        5 => Some("awful"),
+        _ => None,
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Daylio {
@@ -131,6 +149,460 @@ impl Default for Daylio {
     }
 }
 
+/// A problem found by [`Daylio::validate`], which may prevent the app from restoring a backup.
+/// `entry` is the index of the offending entry in [`Daylio::day_entries`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    MissingPredefinedMood(i64),
+    DuplicateMoodId(i64),
+    DuplicateTagId(i64),
+    DuplicateEntryId { entry: usize, id: i64 },
+    UnknownMood { entry: usize, mood: i64 },
+    UnknownTag { entry: usize, tag: i64 },
+    UnknownTagGroup { tag: i64, group: i64 },
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationError::MissingPredefinedMood(id) => {
+                write!(f, "predefined mood {id} is missing")
+            }
+            ValidationError::DuplicateMoodId(id) => write!(f, "several moods have the id {id}"),
+            ValidationError::DuplicateTagId(id) => write!(f, "several tags have the id {id}"),
+            ValidationError::DuplicateEntryId { entry, id } => {
+                write!(
+                    f,
+                    "entry {entry} has the id {id}, already used by another entry"
+                )
+            }
+            ValidationError::UnknownMood { entry, mood } => {
+                write!(f, "entry {entry} uses the unknown mood {mood}")
+            }
+            ValidationError::UnknownTag { entry, tag } => {
+                write!(f, "entry {entry} uses the unknown tag {tag}")
+            }
+            ValidationError::UnknownTagGroup { tag, group } => {
+                write!(f, "tag {tag} belongs to the unknown group {group}")
+            }
+        }
+    }
+}
+
+/// Sections of a backup kept by [`Daylio::subset`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct SectionFlags {
+    /// Custom moods. Predefined moods are always kept
+    pub moods: bool,
+    /// Tags and tag groups
+    pub tags: bool,
+    /// Day entries, with their photos and goal history
+    pub entries: bool,
+    pub writing_templates: bool,
+    pub reminders: bool,
+}
+
+impl SectionFlags {
+    pub const ALL: Self = Self {
+        moods: true,
+        tags: true,
+        entries: true,
+        writing_templates: true,
+        reminders: true,
+    };
+}
+
+impl Daylio {
+    /// Indices of the entries dated after `now`.
+    /// These are usually caused by corrupted timestamps
+    #[must_use]
+    pub fn future_entries(&self, now: DateTime<Utc>) -> Vec<usize> {
+        let now = now.timestamp_millis();
+        self.day_entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.datetime > now)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Removes the entries dated after `now`, and returns how many were removed
+    pub fn drop_future_entries(&mut self, now: DateTime<Utc>) -> usize {
+        let now = now.timestamp_millis();
+        let len = self.day_entries.len();
+        self.day_entries.retain(|entry| entry.datetime <= now);
+        self.metadata.number_of_entries = self.day_entries.len() as i64;
+
+        len - self.day_entries.len()
+    }
+
+    /// Removes the entries carrying no data: no known mood, no tags, no assets and a blank note.
+    /// Moods and tags are kept, as they are part of the app settings.
+    /// Returns how many entries were removed
+    pub fn drop_empty_entries(&mut self) -> usize {
+        let len = self.day_entries.len();
+        let moods = &self.custom_moods;
+        self.day_entries.retain(|entry| {
+            moods.iter().any(|mood| mood.id == entry.mood)
+                || !entry.tags.is_empty()
+                || !entry.assets.is_empty()
+                || !entry.note.trim().is_empty()
+                || !entry.note_title.trim().is_empty()
+        });
+        self.metadata.number_of_entries = self.day_entries.len() as i64;
+
+        len - self.day_entries.len()
+    }
+
+    /// Removes the custom moods and the tags that no entry uses. Predefined moods are always kept.
+    /// Returns how many moods and tags were removed
+    pub fn prune_unused(&mut self) -> (usize, usize) {
+        let entries = &self.day_entries;
+
+        let moods = self.custom_moods.len();
+        self.custom_moods.retain(|mood| {
+            mood.predefined_name_id != -1 || entries.iter().any(|entry| entry.mood == mood.id)
+        });
+
+        let tags = self.tags.len();
+        self.tags
+            .retain(|tag| entries.iter().any(|entry| entry.tags.contains(&tag.id)));
+
+        (moods - self.custom_moods.len(), tags - self.tags.len())
+    }
+
+    /// Normalizes line endings to `\n` and removes trailing whitespace from notes and titles
+    pub fn normalize_notes(&mut self) {
+        fn normalize(text: &str) -> String {
+            text.lines()
+                .map(str::trim_end)
+                .collect::<Vec<_>>()
+                .join("\n")
+                .trim_end()
+                .to_owned()
+        }
+
+        for entry in &mut self.day_entries {
+            entry.note = normalize(&entry.note);
+            entry.note_title = normalize(&entry.note_title);
+        }
+    }
+
+    /// Appends a writing template after the existing ones.
+    /// Orders are made contiguous, as the app expects
+    pub fn add_writing_template(&mut self, title: String, body: String) {
+        self.writing_templates
+            .sort_by_key(|template| template.order);
+        for (i, template) in self.writing_templates.iter_mut().enumerate() {
+            template.order = i as i64;
+        }
+
+        let id = self
+            .writing_templates
+            .iter()
+            .map(|template| template.id)
+            .max()
+            .unwrap_or(0)
+            + 1;
+
+        self.writing_templates.push(WritingTemplate {
+            id,
+            order: self.writing_templates.len() as i64,
+            predefined_template_id: -1,
+            title,
+            body,
+        });
+    }
+
+    /// Adds a reminder at `hour:minute`, enabled and without custom text
+    pub fn add_reminder(&mut self, hour: i64, minute: i64) -> Result<()> {
+        if !(0..24).contains(&hour) || !(0..60).contains(&minute) {
+            return Err(eyre!("Invalid reminder time: {hour:02}:{minute:02}"));
+        }
+
+        let id = self
+            .reminders
+            .iter()
+            .map(|reminder| reminder.id)
+            .max()
+            .unwrap_or(0)
+            + 1;
+
+        self.reminders.push(Reminder {
+            id,
+            hour,
+            minute,
+            state: 0,
+            custom_text_enabled: false,
+        });
+        self.is_reminder_on = true;
+
+        Ok(())
+    }
+
+    /// Looks for the inconsistencies that make the app refuse a backup:
+    /// missing predefined moods, duplicate ids, and references to unknown moods, tags or groups
+    #[must_use]
+    pub fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+
+        for id in 1..=NUMBER_OF_PREDEFINED_MOODS {
+            if !self
+                .custom_moods
+                .iter()
+                .any(|mood| mood.predefined_name_id == id)
+            {
+                errors.push(ValidationError::MissingPredefinedMood(id));
+            }
+        }
+
+        let mut mood_ids = BTreeSet::new();
+        for mood in &self.custom_moods {
+            if !mood_ids.insert(mood.id) {
+                errors.push(ValidationError::DuplicateMoodId(mood.id));
+            }
+        }
+
+        let mut tag_ids = BTreeSet::new();
+        for tag in &self.tags {
+            if !tag_ids.insert(tag.id) {
+                errors.push(ValidationError::DuplicateTagId(tag.id));
+            }
+        }
+
+        // older backups have no tag groups at all, and the app accepts their tags in group 0
+        for tag in &self.tags {
+            let ungrouped = self.tag_groups.is_empty() && tag.id_tag_group == 0;
+            if !ungrouped
+                && !self
+                    .tag_groups
+                    .iter()
+                    .any(|group| group.id == tag.id_tag_group)
+            {
+                errors.push(ValidationError::UnknownTagGroup {
+                    tag: tag.id,
+                    group: tag.id_tag_group,
+                });
+            }
+        }
+
+        let mut entry_ids = BTreeSet::new();
+        for (i, entry) in self.day_entries.iter().enumerate() {
+            if !entry_ids.insert(entry.id) {
+                errors.push(ValidationError::DuplicateEntryId {
+                    entry: i,
+                    id: entry.id,
+                });
+            }
+            if !mood_ids.contains(&entry.mood) {
+                errors.push(ValidationError::UnknownMood {
+                    entry: i,
+                    mood: entry.mood,
+                });
+            }
+            for &tag in &entry.tags {
+                if !tag_ids.contains(&tag) {
+                    errors.push(ValidationError::UnknownTag { entry: i, tag });
+                }
+            }
+        }
+
+        errors
+    }
+
+    /// Same as `==`, but entries, moods and tags can be in any order.
+    /// Useful to compare the results of operations that may reorder them
+    #[must_use]
+    pub fn eq_ignore_order(&self, other: &Daylio) -> bool {
+        let without_lists = |daylio: &Daylio| Daylio {
+            custom_moods: vec![],
+            tags: vec![],
+            day_entries: vec![],
+            ..daylio.clone()
+        };
+
+        same_elements(&self.day_entries, &other.day_entries)
+            && same_elements(&self.custom_moods, &other.custom_moods)
+            && same_elements(&self.tags, &other.tags)
+            && without_lists(self) == without_lists(other)
+    }
+
+    /// Copies the sections selected by `sections`, e.g. moods and tags without entries,
+    /// to share a setup. Kept entries using a dropped custom mood get the predefined mood
+    /// of the same group
+    #[must_use]
+    pub fn subset(&self, sections: SectionFlags) -> Daylio {
+        let mut subset = self.clone();
+        let predefined = |mood: &CustomMood| mood.predefined_name_id != -1;
+
+        if !sections.moods {
+            for entry in &mut subset.day_entries {
+                let Some(mood) = self.custom_moods.iter().find(|mood| mood.id == entry.mood) else {
+                    continue;
+                };
+                if let Some(replacement) = self
+                    .custom_moods
+                    .iter()
+                    .find(|other| predefined(other) && other.mood_group_id == mood.mood_group_id)
+                {
+                    entry.mood = replacement.id;
+                }
+            }
+            subset.custom_moods.retain(predefined);
+        }
+
+        if !sections.tags {
+            subset.tags.clear();
+            subset.tag_groups = Daylio::default().tag_groups;
+            for entry in &mut subset.day_entries {
+                entry.tags.clear();
+            }
+        }
+
+        if !sections.entries {
+            subset.day_entries.clear();
+            subset.assets.clear();
+            subset.goal_entries.clear();
+            subset.goal_success_weeks.clear();
+            subset.days_in_row_longest_chain = 0;
+            subset.metadata.number_of_entries = 0;
+            subset.metadata.number_of_photos = 0;
+            subset.metadata.photos_size = 0;
+        }
+
+        if !sections.writing_templates {
+            subset.writing_templates.clear();
+        }
+
+        if !sections.reminders {
+            subset.reminders.clear();
+            subset.is_reminder_on = false;
+        }
+
+        subset
+    }
+
+    /// Adds the tag named `name` to every entry matching `predicate`, creating the tag if needed.
+    /// Returns how many entries were tagged
+    pub fn add_tag_where(&mut self, name: &str, predicate: impl Fn(&DayEntry) -> bool) -> usize {
+        // tags are compared case-insensitively, as in merge
+        let existing = self
+            .tags
+            .iter()
+            .find(|tag| tag.name.to_lowercase() == name.to_lowercase())
+            .map(|tag| tag.id);
+
+        let id = existing.unwrap_or_else(|| {
+            let id = self.tags.iter().map(|tag| tag.id).max().unwrap_or(0) + 1;
+            let created_at = self
+                .tags
+                .iter()
+                .map(|tag| tag.created_at)
+                .max()
+                .unwrap_or(0)
+                + 1;
+            let group = self
+                .tag_groups
+                .iter()
+                .min_by_key(|group| group.order)
+                .map_or(1, |group| group.id);
+
+            self.tags.push(Tag {
+                id,
+                name: name.to_owned(),
+                created_at,
+                icon: 1,
+                // after the last tag, which may not be the last one of the list
+                order: self.tags.iter().map(|tag| tag.order).max().unwrap_or(0) + 1,
+                state: 0,
+                id_tag_group: group,
+            });
+            id
+        });
+
+        let mut tagged = 0;
+        for entry in &mut self.day_entries {
+            if !entry.tags.contains(&id) && predicate(entry) {
+                entry.tags.push(id);
+                tagged += 1;
+            }
+        }
+
+        tagged
+    }
+}
+
+impl DayEntry {
+    /// Local date of the entry, `None` if its fields are out of range
+    #[must_use]
+    pub fn date(&self) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(
+            i32::try_from(self.year).ok()?,
+            // month is 0-indexed in Daylio
+            u32::try_from(self.month + 1).ok()?,
+            u32::try_from(self.day).ok()?,
+        )
+    }
+}
+
+/// Whether both slices hold the same elements, the same number of times, in any order
+fn same_elements<T: PartialEq>(items1: &[T], items2: &[T]) -> bool {
+    if items1.len() != items2.len() {
+        return false;
+    }
+
+    let mut matched = vec![false; items2.len()];
+    items1.iter().all(|item1| {
+        let found = items2
+            .iter()
+            .zip(&mut matched)
+            .find(|(item2, matched)| !**matched && item1 == *item2);
+
+        if let Some((_, matched)) = found {
+            *matched = true;
+            true
+        } else {
+            false
+        }
+    })
+}
+
+/// A short summary, more readable than the `Debug` output
+impl Display for Daylio {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Daylio backup, version {}", self.version)?;
+
+        // month is 0-indexed in Daylio
+        let dates = self
+            .day_entries
+            .iter()
+            .map(|entry| (entry.year, entry.month + 1, entry.day));
+        match (dates.clone().min(), dates.max()) {
+            (Some((y1, m1, d1)), Some((y2, m2, d2))) => writeln!(
+                f,
+                "{} entries, from {y1:04}-{m1:02}-{d1:02} to {y2:04}-{m2:02}-{d2:02}",
+                self.day_entries.len()
+            )?,
+            _ => writeln!(f, "0 entries")?,
+        }
+
+        let moods = self
+            .custom_moods
+            .iter()
+            .map(CustomMood::display_name)
+            .collect::<Vec<_>>();
+        writeln!(f, "{} moods: {}", moods.len(), moods.join(", "))?;
+
+        let tags = self
+            .tags
+            .iter()
+            .map(|tag| tag.name.as_str())
+            .collect::<Vec<_>>();
+        write!(f, "{} tags: {}", tags.len(), tags.join(", "))
+    }
+}
+
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CustomMood {
@@ -149,6 +621,20 @@ pub struct CustomMood {
     pub created_at: i64,
 }
 
+impl CustomMood {
+    /// Predefined moods have no name, the app translates them. We use the English names
+    pub(crate) fn display_name(&self) -> String {
+        if !self.custom_name.is_empty() {
+            return self.custom_name.clone();
+        }
+
+        predefined_mood_name_en(self.predefined_name_id).map_or_else(
+            || format!("predefined {}", self.predefined_name_id),
+            ToOwned::to_owned,
+        )
+    }
+}
+
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Tag {
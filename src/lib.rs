@@ -5,12 +5,18 @@
 #![allow(clippy::cast_possible_wrap)]
 
 pub use anonymize::anonymize;
+pub use csv::{CsvNewlines, CsvOptions};
 pub use daylio::*;
 pub use load_store::*;
-pub use merge::merge;
+pub use merge::{
+    DayEntryComparisonPolicy, largest_daylio, merge, merge_with_explanation, merge_with_options,
+    merge_with_policy, MergeOptions, SimplifyOptions,
+};
+pub use parse_pdf::{parse_pdf, ParsedDayEntry, ParsedPdf, StatLine};
 
 mod analyze_pdf;
 mod anonymize;
+mod csv;
 mod daylio;
 mod load_store;
 mod merge;
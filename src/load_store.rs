@@ -1,32 +1,73 @@
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
-use color_eyre::eyre::{ContextCompat, eyre, WrapErr};
+use base64::{
+    Engine,
+    engine::general_purpose::{STANDARD as BASE64, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD},
+};
+use color_eyre::eyre::{eyre, WrapErr};
 use color_eyre::Result;
 use zip::write::SimpleFileOptions;
 use zip::ZipWriter;
 
 use crate::analyze_pdf::ProcessedPdf;
-use crate::Daylio;
+use crate::csv::CsvOptions;
+use crate::{DayEntry, Daylio};
+
+/// Default limit of [`load_daylio_backup_with_limit`]. Real backups are a few MiB at most
+pub const DEFAULT_MAX_BACKUP_SIZE: u64 = 256 * 1024 * 1024;
 
 pub fn load_daylio_backup(path: &Path) -> Result<Daylio> {
+    load_daylio_backup_with_limit(path, DEFAULT_MAX_BACKUP_SIZE)
+}
+
+/// Loads a backup, refusing to decompress more than `max_size` bytes,
+/// so that a malicious archive cannot exhaust memory
+pub fn load_daylio_backup_with_limit(path: &Path, max_size: u64) -> Result<Daylio> {
     let file = File::open(path)?;
 
     let mut archive = zip::ZipArchive::new(file)?;
-    let mut file = archive.by_name("backup.daylio")?;
+    let file = archive.by_name("backup.daylio")?;
 
-    let mut data = String::new();
-    file.read_to_string(&mut data)?;
+    let too_large = || eyre!("Backup is larger than the limit of {max_size} bytes");
+    if file.size() > max_size {
+        return Err(too_large());
+    }
+
+    // the reported size can be forged, so also enforce the limit while reading
+    let mut data = Vec::new();
+    file.take(max_size.saturating_add(1))
+        .read_to_end(&mut data)?;
+    if data.len() as u64 > max_size {
+        return Err(too_large());
+    }
+
+    let mut data = String::from_utf8(data)?;
     data = data.replace('\n', "");
 
-    let data = BASE64.decode(data)?;
+    let data = decode_base64(&data)?;
 
     serde_json::from_slice(&data).wrap_err("Failed to parse Daylio backup")
 }
 
+/// The app uses standard base64, but some tools write the URL-safe variant or omit padding
+fn decode_base64(data: &str) -> Result<Vec<u8>> {
+    let fallbacks = [&URL_SAFE, &STANDARD_NO_PAD, &URL_SAFE_NO_PAD];
+
+    let decoded = BASE64.decode(data).or_else(|error| {
+        fallbacks
+            .iter()
+            .find_map(|engine| engine.decode(data).ok())
+            .ok_or(error)
+    })?;
+
+    Ok(decoded)
+}
+
 pub fn load_daylio_json(path: &Path) -> Result<Daylio> {
     let mut file = File::open(path)?;
     let mut data = String::new();
@@ -35,31 +76,135 @@ pub fn load_daylio_json(path: &Path) -> Result<Daylio> {
     serde_json::from_str(&data).wrap_err("Failed to parse Daylio JSON")
 }
 
+/// Loads the CSV export of Daylio. It has less information than a backup:
+/// custom moods get a default group and tags get a default icon
+pub fn load_daylio_csv(path: &Path) -> Result<Daylio> {
+    load_daylio_csv_with_options(path, CsvOptions::default())
+}
+
+/// Same as [`load_daylio_csv`], for a CSV written with other settings, e.g. another delimiter
+pub fn load_daylio_csv_with_options(path: &Path, options: CsvOptions) -> Result<Daylio> {
+    let mut file = File::open(path)?;
+    let mut data = String::new();
+    file.read_to_string(&mut data)?;
+
+    crate::csv::parse_csv(&data, options).wrap_err("Failed to parse Daylio CSV")
+}
+
 pub fn load_daylio_pdf(path: &Path) -> Result<Daylio> {
-    crate::parse_pdf::parse_pdf(path)
-        .map(Into::<ProcessedPdf>::into)
-        .map(Into::into)
+    load_daylio_pdf_with_progress(path, |_| {})
+}
+
+/// Same as [`load_daylio_pdf`], but `progress` is called with the number of day entries parsed so far
+pub fn load_daylio_pdf_with_progress(
+    path: &Path,
+    mut progress: impl FnMut(usize),
+) -> Result<Daylio> {
+    let mut parsed_entries = 0;
+    let parsed = crate::parse_pdf::parse_pdf_with_progress(path, |_| {
+        parsed_entries += 1;
+        progress(parsed_entries);
+    })?;
+
+    Ok(ProcessedPdf::try_from(parsed)?.into())
+}
+
+/// Same as [`load_daylio_pdf`], but day entries that cannot be parsed are skipped.
+/// Also returns a warning for each skipped part of the PDF
+pub fn load_daylio_pdf_recovering(path: &Path) -> Result<(Daylio, Vec<String>)> {
+    load_daylio_pdf_with_dumps(path, &PdfDumps::default(), true)
+}
+
+/// Where to write the intermediate steps of a PDF conversion, see [`load_daylio_pdf_with_dumps`]
+#[derive(Clone, Debug, Default)]
+pub struct PdfDumps {
+    /// The text extracted from the PDF, before any parsing. Useful to debug parsing failures
+    pub text: Option<PathBuf>,
+    /// The PDF as parsed, before it is interpreted as a Daylio backup.
+    /// Useful to tell parsing bugs from interpretation bugs
+    pub parsed: Option<PathBuf>,
+}
+
+/// Loads a PDF, writing the requested dumps on the way. The PDF is only read and parsed once.
+/// With `recover`, behaves like [`load_daylio_pdf_recovering`]
+pub fn load_daylio_pdf_with_dumps(
+    path: &Path,
+    dumps: &PdfDumps,
+    recover: bool,
+) -> Result<(Daylio, Vec<String>)> {
+    let text = crate::parse_pdf::extract_txt(path)?;
+    // written before parsing, so that it is available when parsing fails
+    if let Some(dump) = &dumps.text {
+        let mut file = File::create(dump)?;
+        file.write_all(text.as_bytes())?;
+    }
+
+    let mut parsed = crate::parse_pdf::parse_text(&text, recover, |_| {})?;
+    if let Some(dump) = &dumps.parsed {
+        let json = serde_json::to_string_pretty(&parsed)?;
+
+        let mut file = File::create(dump)?;
+        file.write_all(json.as_bytes())?;
+    }
+
+    let warnings = std::mem::take(&mut parsed.warnings);
+
+    Ok((ProcessedPdf::try_from(parsed)?.into(), warnings))
 }
 
+/// The formats a file can be loaded from, with their name and extension
+const FORMATS: [(&str, &str, fn(&Path) -> Result<Daylio>); 4] = [
+    ("Daylio backup", "daylio", load_daylio_backup),
+    ("JSON", "json", load_daylio_json),
+    ("PDF", "pdf", load_daylio_pdf),
+    ("CSV", "csv", load_daylio_csv),
+];
+
+/// Loads a file according to its extension, or tries every format if the extension is unknown
 pub fn load_daylio(path: &Path) -> Result<Daylio> {
-    if let Some(ext) = path.extension() {
-        let ext = ext.to_str().wrap_err("Unknown file extension")?;
-        match ext.to_lowercase().as_ref() {
-            "daylio" => load_daylio_backup(path),
-            "json" => load_daylio_json(path),
-            "pdf" => load_daylio_pdf(path),
-            _ => Err(eyre!("Unknown file extension")),
+    load_daylio_with_format(path).map(|(daylio, _)| daylio)
+}
+
+/// Same as [`load_daylio`], but also returns the name of the format the file was loaded from
+pub fn load_daylio_with_format(path: &Path) -> Result<(Daylio, &'static str)> {
+    let ext = path
+        .extension()
+        .and_then(OsStr::to_str)
+        .map(str::to_lowercase);
+
+    match FORMATS
+        .iter()
+        .find(|(_, extension, _)| ext.as_deref() == Some(*extension))
+    {
+        Some(&(format, _, loader)) => Ok((loader(path)?, format)),
+        None => load_daylio_any_with_format(path),
+    }
+}
+
+/// Ignores the extension and tries each format in turn, which helps with mislabeled files.
+/// If none works, the error lists why each format failed
+pub fn load_daylio_any(path: &Path) -> Result<Daylio> {
+    load_daylio_any_with_format(path).map(|(daylio, _)| daylio)
+}
+
+fn load_daylio_any_with_format(path: &Path) -> Result<(Daylio, &'static str)> {
+    let mut errors = Vec::new();
+    for (format, _, loader) in FORMATS {
+        match loader(path) {
+            Ok(daylio) => return Ok((daylio, format)),
+            Err(error) => errors.push(format!("{format}: {error}")),
         }
-    } else {
-        Err(eyre!("Missing file extension"))
     }
+
+    Err(eyre!("Unknown file format, tried:\n{}", errors.join("\n")))
 }
 
+/// Writes a backup the app can restore. Like the app, the archive is compressed with Deflate
 pub fn store_daylio_backup(daylio: &Daylio, path: &Path) -> Result<()> {
     let file = File::create(path)?;
 
     let mut archive = ZipWriter::new(file);
-    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
 
     let json = serde_json::to_string_pretty(daylio)?;
 
@@ -80,3 +225,43 @@ pub fn store_daylio_json(daylio: &Daylio, path: &Path) -> Result<()> {
 
     Ok(())
 }
+
+/// Writes the day entries with the columns of the official CSV export
+pub fn store_daylio_csv(daylio: &Daylio, path: &Path) -> Result<()> {
+    store_daylio_csv_with_options(daylio, path, CsvOptions::default())
+}
+
+/// Same as [`store_daylio_csv`], with `options` adapting the CSV to the tool reading it
+pub fn store_daylio_csv_with_options(
+    daylio: &Daylio,
+    path: &Path,
+    options: CsvOptions,
+) -> Result<()> {
+    let csv = crate::csv::write_csv(daylio, options)?;
+
+    let mut file = File::create(path)?;
+    file.write_all(csv.as_bytes())?;
+
+    Ok(())
+}
+
+/// Writes only the day entries, as an object mapping each YYYY-MM-DD day to its entries,
+/// in chronological order
+pub fn store_daylio_grouped_json(daylio: &Daylio, path: &Path) -> Result<()> {
+    let mut entries = daylio.day_entries.iter().collect::<Vec<_>>();
+    entries.sort_by_key(|entry| entry.datetime);
+
+    let mut days: BTreeMap<String, Vec<&DayEntry>> = BTreeMap::new();
+    for entry in entries {
+        // month is 0-indexed in Daylio
+        let day = format!("{:04}-{:02}-{:02}", entry.year, entry.month + 1, entry.day);
+        days.entry(day).or_default().push(entry);
+    }
+
+    let json = serde_json::to_string_pretty(&days)?;
+
+    let mut file = File::create(path)?;
+    file.write_all(json.as_bytes())?;
+
+    Ok(())
+}
@@ -1,14 +1,46 @@
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
 use color_eyre::eyre::{ContextCompat, Result};
 
-use daylio_tools::{load_daylio, merge, store_daylio_backup, store_daylio_json};
+use daylio_tools::{
+    CsvNewlines, CsvOptions, Daylio, DayEntryComparisonPolicy, largest_daylio, load_daylio,
+    load_daylio_csv_with_options, load_daylio_pdf_with_dumps, load_daylio_with_format,
+    merge_with_explanation, MergeOptions, PdfDumps, SectionFlags, SimplifyOptions,
+    store_daylio_backup, store_daylio_csv_with_options, store_daylio_grouped_json,
+    store_daylio_json,
+};
+
+/// Options for the commands converting a single file
+#[derive(Default)]
+struct ConvertOptions {
+    drop_future: bool,
+    normalize_notes: bool,
+    drop_empty: bool,
+    prune_unused: bool,
+    /// Where to write the intermediate steps of a PDF input
+    dumps: PdfDumps,
+    /// Extract only the entries, grouped by day
+    group_by_day: bool,
+    /// Skip the PDF entries that cannot be parsed instead of failing
+    recover: bool,
+    /// How CSV inputs are read and CSV outputs written
+    csv: CsvOptions,
+}
 
 enum Command {
     Merge {
         input: Vec<PathBuf>,
         output: PathBuf,
+        /// Use the input with the most entries as reference instead of the first one
+        auto_reference: bool,
+        /// Print why entries were or were not considered duplicates
+        explain: bool,
+        drop_empty: bool,
+        policy: DayEntryComparisonPolicy,
+        options: MergeOptions,
     },
     Anonymize {
         input: PathBuf,
@@ -17,19 +49,101 @@ enum Command {
     Extract {
         input: PathBuf,
         output: PathBuf,
+        options: ConvertOptions,
     },
     Pack {
         input: PathBuf,
         output: PathBuf,
+        options: ConvertOptions,
+    },
+    Templates {
+        input: PathBuf,
+        /// Title and body of the template to add, and where to write the result
+        add: Option<(String, String, PathBuf)>,
+    },
+    Reminders {
+        input: PathBuf,
+        /// Hour and minute of the reminder to add, and where to write the result
+        set: Option<(i64, i64, PathBuf)>,
+    },
+    Info {
+        input: PathBuf,
+    },
+    /// Lists the problems that may prevent the app from restoring a backup
+    Validate {
+        input: PathBuf,
+    },
+    Tag {
+        input: PathBuf,
+        output: PathBuf,
+        tag: String,
+        /// Only tag entries whose note or title contains this text, ignoring case
+        contains: Option<String>,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    },
+    /// Copies a backup, optionally without its entries, to share moods and tags
+    Subset {
+        input: PathBuf,
+        output: PathBuf,
+        no_entries: bool,
     },
 }
 
+/// Flags taking the next argument as their value
+const VALUE_FLAGS: &[&str] = &[
+    "--add",
+    "--set",
+    "--dump-text",
+    "--dump-parsed",
+    "--tag",
+    "--contains",
+    "--from",
+    "--to",
+    "--same-day-hours",
+    "--fuzzy",
+    "--csv-delim",
+    "--csv-newlines",
+];
+
+type Flag = (String, Option<String>);
+
+/// Separates positional arguments from `--flags`
+fn split_args(mut args: impl Iterator<Item = String>) -> Result<(Vec<String>, Vec<Flag>)> {
+    let mut positional = Vec::new();
+    let mut flags = Vec::new();
+
+    while let Some(arg) = args.next() {
+        if VALUE_FLAGS.contains(&arg.as_str()) {
+            let value = args
+                .next()
+                .ok_or_else(|| color_eyre::eyre::eyre!("Missing value for {arg}"))?;
+            flags.push((arg, Some(value)));
+        } else if arg.starts_with("--") {
+            flags.push((arg, None));
+        } else {
+            positional.push(arg);
+        }
+    }
+
+    Ok((positional, flags))
+}
+
+/// For commands accepting a single optional flag, which must be `name`
+fn only_value_flag<'a>(flags: &'a [Flag], name: &str) -> Result<Option<&'a str>> {
+    match flags {
+        [] => Ok(None),
+        [(flag, Some(value))] if flag == name => Ok(Some(value)),
+        [(flag, _), ..] => Err(color_eyre::eyre::eyre!("Unknown flag: {flag}")),
+    }
+}
+
 fn parse_args() -> Result<Command> {
-    let args: Vec<String> = env::args().collect();
+    let (args, flags) = split_args(env::args())?;
 
     let command = args.get(1).ok_or_else(|| {
         color_eyre::eyre::eyre!(
-            "Missing command. Usage: daylio-tools <command> <input(s)> <output>"
+            "Missing command. Usage: daylio-tools <command> <input(s)> <output> [--flags]"
         )
     })?;
 
@@ -47,8 +161,94 @@ fn parse_args() -> Result<Command> {
         Ok((input, output))
     };
 
+    let get_convert_options = || -> Result<ConvertOptions> {
+        let mut options = ConvertOptions::default();
+        for (flag, value) in &flags {
+            match (flag.as_str(), value) {
+                ("--drop-future", _) => options.drop_future = true,
+                ("--normalize-notes", _) => options.normalize_notes = true,
+                ("--drop-empty", _) => options.drop_empty = true,
+                ("--prune-unused", _) => options.prune_unused = true,
+                ("--group-by-day", _) => options.group_by_day = true,
+                ("--recover", _) => options.recover = true,
+                ("--dump-text", Some(path)) => options.dumps.text = Some(PathBuf::from(path)),
+                ("--dump-parsed", Some(path)) => options.dumps.parsed = Some(PathBuf::from(path)),
+                ("--csv-delim", Some(delimiter)) => {
+                    options.csv.delimiter = match delimiter.as_bytes() {
+                        [byte] if !matches!(byte, b'"' | b'\n' | b'\r') => *byte,
+                        _ => {
+                            return Err(color_eyre::eyre::eyre!(
+                                "--csv-delim must be a single character, other than a quote"
+                            ))
+                        }
+                    };
+                }
+                ("--csv-newlines", Some(mode)) => {
+                    options.csv.newlines = match mode.as_str() {
+                        "quote" => CsvNewlines::Quote,
+                        "escape" => CsvNewlines::Escape,
+                        "space" => CsvNewlines::Space,
+                        _ => {
+                            return Err(color_eyre::eyre::eyre!(
+                                "--csv-newlines must be quote, escape or space"
+                            ))
+                        }
+                    };
+                }
+                _ => return Err(color_eyre::eyre::eyre!("Unknown flag: {flag}")),
+            }
+        }
+        Ok(options)
+    };
+
+    let no_flags = || -> Result<()> {
+        match flags.first() {
+            Some((flag, _)) => Err(color_eyre::eyre::eyre!("Unknown flag: {flag}")),
+            None => Ok(()),
+        }
+    };
+
     match command.as_str() {
         "merge" => {
+            let mut auto_reference = false;
+            let mut explain = false;
+            let mut drop_empty = false;
+            let mut policy = DayEntryComparisonPolicy::Strict;
+            let mut same_day_threshold = None;
+            for (flag, value) in &flags {
+                match (flag.as_str(), value) {
+                    ("--auto-reference", _) => auto_reference = true,
+                    ("--explain", _) => explain = true,
+                    ("--drop-empty", _) => drop_empty = true,
+                    ("--relaxed", _) => {
+                        policy = DayEntryComparisonPolicy::Relaxed(SimplifyOptions::default());
+                    }
+                    ("--fuzzy", Some(max_distance)) => {
+                        policy = DayEntryComparisonPolicy::Fuzzy {
+                            max_distance: max_distance.parse()?,
+                        };
+                    }
+                    ("--same-day-hours", Some(hours)) => {
+                        same_day_threshold = Some(
+                            TimeDelta::try_hours(hours.parse()?)
+                                .wrap_err("Invalid --same-day-hours")?,
+                        );
+                    }
+                    _ => return Err(color_eyre::eyre::eyre!("Unknown flag: {flag}")),
+                }
+            }
+
+            let mut options = MergeOptions::default();
+            if let Some(threshold) = same_day_threshold {
+                // strict merges only collapse identical entries, whatever their distance
+                if policy == DayEntryComparisonPolicy::Strict {
+                    return Err(color_eyre::eyre::eyre!(
+                        "--same-day-hours requires --relaxed or --fuzzy"
+                    ));
+                }
+                options.same_day_threshold = threshold;
+            }
+
             let mut inputs = args.iter().skip(2).map(PathBuf::from).collect::<Vec<_>>();
             let output = inputs.pop().wrap_err("Missing output file")?; // last one is output
 
@@ -59,9 +259,15 @@ fn parse_args() -> Result<Command> {
             Ok(Command::Merge {
                 input: inputs,
                 output,
+                auto_reference,
+                explain,
+                drop_empty,
+                policy,
+                options,
             })
         }
         "anonymize" => {
+            no_flags()?;
             let args = get_single_in_out()?;
             Ok(Command::Anonymize {
                 input: args.0,
@@ -73,46 +279,383 @@ fn parse_args() -> Result<Command> {
             Ok(Command::Extract {
                 input: args.0,
                 output: args.1,
+                options: get_convert_options()?,
             })
         }
         "pack" => {
             let args = get_single_in_out()?;
+            let options = get_convert_options()?;
+            if options.group_by_day {
+                return Err(color_eyre::eyre::eyre!(
+                    "--group-by-day is only supported by extract"
+                ));
+            }
+
             Ok(Command::Pack {
                 input: args.0,
                 output: args.1,
+                options,
+            })
+        }
+        "templates" => {
+            let input = args
+                .get(2)
+                .ok_or_else(|| color_eyre::eyre::eyre!("Missing input path"))?;
+
+            let add = match only_value_flag(&flags, "--add")? {
+                Some(template) => {
+                    let (title, body) = template
+                        .split_once('|')
+                        .wrap_err("Template must look like \"Title|Body\"")?;
+                    let output = args
+                        .get(3)
+                        .ok_or_else(|| color_eyre::eyre::eyre!("Missing output path"))?;
+                    Some((title.to_owned(), body.to_owned(), PathBuf::from(output)))
+                }
+                None => None,
+            };
+
+            Ok(Command::Templates {
+                input: PathBuf::from(input),
+                add,
+            })
+        }
+        "reminders" => {
+            let input = args
+                .get(2)
+                .ok_or_else(|| color_eyre::eyre::eyre!("Missing input path"))?;
+
+            let set = match only_value_flag(&flags, "--set")? {
+                Some(time) => {
+                    let (hour, minute) = time
+                        .split_once(':')
+                        .wrap_err("Reminder time must look like HH:MM")?;
+                    let output = args
+                        .get(3)
+                        .ok_or_else(|| color_eyre::eyre::eyre!("Missing output path"))?;
+                    Some((hour.parse()?, minute.parse()?, PathBuf::from(output)))
+                }
+                None => None,
+            };
+
+            Ok(Command::Reminders {
+                input: PathBuf::from(input),
+                set,
+            })
+        }
+        "info" => {
+            no_flags()?;
+            let input = args
+                .get(2)
+                .ok_or_else(|| color_eyre::eyre::eyre!("Missing input path"))?;
+            Ok(Command::Info {
+                input: PathBuf::from(input),
+            })
+        }
+        "validate" => {
+            no_flags()?;
+            let input = args
+                .get(2)
+                .ok_or_else(|| color_eyre::eyre::eyre!("Missing input path"))?;
+            Ok(Command::Validate {
+                input: PathBuf::from(input),
+            })
+        }
+        "tag" => {
+            let (input, output) = get_single_in_out()?;
+
+            let mut tag = None;
+            let mut contains = None;
+            let mut from = None;
+            let mut to = None;
+            for (flag, value) in &flags {
+                let parse_date = |date: &str| NaiveDate::parse_from_str(date, "%Y-%m-%d");
+                match (flag.as_str(), value) {
+                    ("--tag", Some(name)) => tag = Some(name.clone()),
+                    ("--contains", Some(text)) => contains = Some(text.clone()),
+                    ("--from", Some(date)) => from = Some(parse_date(date)?),
+                    ("--to", Some(date)) => to = Some(parse_date(date)?),
+                    _ => return Err(color_eyre::eyre::eyre!("Unknown flag: {flag}")),
+                }
+            }
+
+            Ok(Command::Tag {
+                input,
+                output,
+                tag: tag.wrap_err("Missing --tag")?,
+                contains,
+                from,
+                to,
+            })
+        }
+        "subset" => {
+            let (input, output) = get_single_in_out()?;
+
+            let mut no_entries = false;
+            for (flag, _) in &flags {
+                match flag.as_str() {
+                    "--no-entries" => no_entries = true,
+                    _ => return Err(color_eyre::eyre::eyre!("Unknown flag: {flag}")),
+                }
+            }
+
+            Ok(Command::Subset {
+                input,
+                output,
+                no_entries,
             })
         }
         _ => Err(color_eyre::eyre::eyre!("Unknown command")),
     }
 }
 
+fn load_for_convert(input: &Path, options: &ConvertOptions) -> Result<Daylio> {
+    let is_pdf = input
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"));
+
+    let pdf_flags = [
+        ("--dump-text", options.dumps.text.is_some()),
+        ("--dump-parsed", options.dumps.parsed.is_some()),
+        ("--recover", options.recover),
+    ];
+    if let Some((flag, _)) = pdf_flags.iter().find(|(_, used)| *used) {
+        if !is_pdf {
+            return Err(color_eyre::eyre::eyre!("{flag} requires a PDF input"));
+        }
+    }
+
+    let is_csv = input
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+
+    let mut daylio = if is_pdf {
+        // the PDF is read once, for the dumps and the conversion
+        let (daylio, warnings) =
+            load_daylio_pdf_with_dumps(input, &options.dumps, options.recover)?;
+        for warning in warnings {
+            eprintln!("Warning: {warning}");
+        }
+        daylio
+    } else if is_csv {
+        load_daylio_csv_with_options(input, options.csv)?
+    } else {
+        load_daylio(input)?
+    };
+    convert(&mut daylio, options);
+
+    Ok(daylio)
+}
+
+fn convert(daylio: &mut Daylio, options: &ConvertOptions) {
+    let now = DateTime::<Utc>::from(SystemTime::now());
+
+    if options.drop_future {
+        let dropped = daylio.drop_future_entries(now);
+        if dropped > 0 {
+            eprintln!("Dropped {dropped} entries dated in the future");
+        }
+    } else {
+        let future = daylio.future_entries(now).len();
+        if future > 0 {
+            eprintln!(
+                "Warning: {future} entries are dated in the future. Use --drop-future to remove them"
+            );
+        }
+    }
+
+    if options.normalize_notes {
+        daylio.normalize_notes();
+    }
+
+    if options.drop_empty {
+        report_dropped_empty(daylio.drop_empty_entries());
+    }
+
+    // after dropping entries, as it can leave more moods and tags unused
+    if options.prune_unused {
+        let (moods, tags) = daylio.prune_unused();
+        eprintln!("Removed {moods} unused moods and {tags} unused tags");
+    }
+}
+
+fn report_dropped_empty(dropped: usize) {
+    if dropped > 0 {
+        eprintln!("Dropped {dropped} empty entries");
+    }
+}
+
 fn main() -> Result<()> {
     color_eyre::install()?;
 
     let command = parse_args()?;
 
     match command {
-        Command::Merge { input, output } => {
-            let mut reference = load_daylio(&input[0])?;
+        Command::Merge {
+            input,
+            output,
+            auto_reference,
+            explain,
+            drop_empty,
+            policy,
+            options,
+        } => {
+            let mut daylios = input
+                .iter()
+                .map(|path| load_daylio(path))
+                .collect::<Result<Vec<_>>>()?;
 
-            for path in input.iter().skip(1) {
-                let other = load_daylio(path)?;
-                reference = merge(reference, other);
+            if auto_reference {
+                let idx = largest_daylio(&daylios).unwrap_or(0);
+                eprintln!("Using {} as reference", input[idx].display());
+                let reference = daylios.remove(idx);
+                daylios.insert(0, reference);
             }
-            store_daylio_backup(&reference, &output)?;
+
+            let mut merged = daylios
+                .into_iter()
+                .reduce(|reference, other| {
+                    merge_with_explanation(reference, other, policy, options, |line| {
+                        if explain {
+                            println!("{line}");
+                        }
+                    })
+                })
+                .wrap_err("Missing input files")?;
+
+            if drop_empty {
+                report_dropped_empty(merged.drop_empty_entries());
+            }
+            store_daylio_backup(&merged, &output)?;
         }
         Command::Anonymize { input, output } => {
             let mut daylio = load_daylio(&input)?;
             daylio_tools::anonymize(&mut daylio);
             store_daylio_backup(&daylio, &output)?;
         }
-        Command::Extract { input, output } => {
+        Command::Extract {
+            input,
+            output,
+            options,
+        } => {
+            let daylio = load_for_convert(&input, &options)?;
+            let is_csv = output
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+
+            if options.group_by_day {
+                store_daylio_grouped_json(&daylio, &output)?;
+            } else if is_csv {
+                store_daylio_csv_with_options(&daylio, &output, options.csv)?;
+            } else {
+                store_daylio_json(&daylio, &output)?;
+            }
+        }
+        Command::Pack {
+            input,
+            output,
+            options,
+        } => {
+            let daylio = load_for_convert(&input, &options)?;
+            store_daylio_backup(&daylio, &output)?;
+        }
+        Command::Templates { input, add } => {
+            let mut daylio = load_daylio(&input)?;
+
+            if let Some((title, body, output)) = add {
+                daylio.add_writing_template(title, body);
+                store_daylio_backup(&daylio, &output)?;
+            }
+
+            for template in &daylio.writing_templates {
+                println!(
+                    "{}. {}\n{}\n",
+                    template.order, template.title, template.body
+                );
+            }
+        }
+        Command::Reminders { input, set } => {
+            let mut daylio = load_daylio(&input)?;
+
+            if let Some((hour, minute, output)) = set {
+                daylio.add_reminder(hour, minute)?;
+                store_daylio_backup(&daylio, &output)?;
+            }
+
+            for reminder in &daylio.reminders {
+                println!(
+                    "{:02}:{:02} (state {})",
+                    reminder.hour, reminder.minute, reminder.state
+                );
+            }
+        }
+        Command::Tag {
+            input,
+            output,
+            tag,
+            contains,
+            from,
+            to,
+        } => {
+            let mut daylio = load_daylio(&input)?;
+
+            let contains = contains.map(|text| text.to_lowercase());
+            let tagged = daylio.add_tag_where(&tag, |entry| {
+                let in_range = entry.date().is_some_and(|date| {
+                    from.is_none_or(|from| date >= from) && to.is_none_or(|to| date <= to)
+                });
+                let matches = contains.as_ref().is_none_or(|text| {
+                    entry.note.to_lowercase().contains(text)
+                        || entry.note_title.to_lowercase().contains(text)
+                });
+                in_range && matches
+            });
+            println!("Tagged {tagged} entries with {tag}");
+
+            store_daylio_backup(&daylio, &output)?;
+        }
+        Command::Subset {
+            input,
+            output,
+            no_entries,
+        } => {
             let daylio = load_daylio(&input)?;
-            store_daylio_json(&daylio, &output)?;
+
+            let sections = SectionFlags {
+                entries: !no_entries,
+                ..SectionFlags::ALL
+            };
+            store_daylio_backup(&daylio.subset(sections), &output)?;
         }
-        Command::Pack { input, output } => {
+        Command::Info { input } => {
+            let (daylio, format) = load_daylio_with_format(&input)?;
+
+            println!("Format: {format}");
+            println!("{daylio}");
+
+            let problems = daylio.validate().len();
+            if problems == 0 {
+                println!("Valid: yes");
+            } else {
+                println!("Valid: no, {problems} problems. Use validate to list them");
+            }
+        }
+        Command::Validate { input } => {
             let daylio = load_daylio(&input)?;
-            store_daylio_backup(&daylio, &output)?;
+
+            let errors = daylio.validate();
+            for error in &errors {
+                println!("{error}");
+            }
+            // a future date is suspicious, but the backup can still be restored
+            let now = DateTime::<Utc>::from(SystemTime::now());
+            for entry in daylio.future_entries(now) {
+                println!("Warning: entry {entry} is dated in the future");
+            }
+            if !errors.is_empty() {
+                return Err(color_eyre::eyre::eyre!("Found {} problems", errors.len()));
+            }
+            println!("No problem found");
         }
     }
 
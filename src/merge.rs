@@ -1,6 +1,50 @@
+use std::cmp::Ordering;
+
+use chrono::{DateTime, TimeDelta};
+
 use crate::{DayEntry, NUMBER_OF_PREDEFINED_MOODS};
 use crate::daylio::{CustomMood, Daylio, Tag};
 
+/// How day entries are compared when looking for duplicates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DayEntryComparisonPolicy {
+    /// Entries have to be exactly the same
+    #[default]
+    Strict,
+    /// Entries closer than [`MergeOptions::same_day_threshold`], with the same mood and tags,
+    /// are duplicates if their notes only differ by case, whitespace or punctuation.
+    /// The longer note is kept
+    Relaxed(SimplifyOptions),
+    /// Same as [`DayEntryComparisonPolicy::Relaxed`] with the default [`SimplifyOptions`],
+    /// but notes also match if they are at most `max_distance` edits apart, e.g. a typo fix
+    Fuzzy { max_distance: usize },
+}
+
+/// What else is ignored when comparing notes with [`DayEntryComparisonPolicy::Relaxed`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SimplifyOptions {
+    /// Compare accented letters as their base letter, so that "Élan" matches "elan"
+    pub fold_accents: bool,
+    /// Drop digits, so that "3 km" matches "km"
+    pub ignore_digits: bool,
+}
+
+/// Settings of the merge that do not depend on the [`DayEntryComparisonPolicy`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MergeOptions {
+    /// Entries further apart are never duplicates. PDF exports lose minute precision,
+    /// so a wider window can help, while a narrower one makes the matching stricter
+    pub same_day_threshold: TimeDelta,
+}
+
+impl Default for MergeOptions {
+    fn default() -> Self {
+        Self {
+            same_day_threshold: TimeDelta::days(1),
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 struct IdGenerator {
     offset: i64,
@@ -85,7 +129,16 @@ impl Daylio {
         }
     }
 
-    fn remove_duplicates(&mut self) {
+    /// The first `first_entries` day entries come from the first file, the others from the second
+    fn remove_duplicates(
+        &mut self,
+        first_entries: usize,
+        policy: DayEntryComparisonPolicy,
+        options: MergeOptions,
+        explain: &mut dyn FnMut(&str),
+    ) {
+        let threshold = options.same_day_threshold.num_milliseconds();
+
         // for moods
         self.custom_moods.sort_by_key(ProjectEq::project);
 
@@ -112,20 +165,114 @@ impl Daylio {
 
         self.tags.retain(|tag| tag.id != -1);
 
-        // for entries
-        self.day_entries
-            .sort_by_key(|x| (x.datetime, x.year, x.month));
-
-        for i in 1..self.day_entries.len() {
-            // we do not want to lose any data, so they need to be exactly the same
-            if self.day_entries[i - 1] == self.day_entries[i] {
-                self.day_entries[i].id = -1; // mark for deletion
+        // for entries, remembering which file each one comes from
+        let mut entries = std::mem::take(&mut self.day_entries)
+            .into_iter()
+            .enumerate()
+            .map(|(i, entry)| (i < first_entries, entry))
+            .collect::<Vec<_>>();
+        entries.sort_by(|(_, entry1), (_, entry2)| compare_entries(entry1, entry2));
+        let (from_first, day_entries): (Vec<_>, Vec<_>) = entries.into_iter().unzip();
+        self.day_entries = day_entries;
+
+        match policy {
+            DayEntryComparisonPolicy::Strict => {
+                for i in 1..self.day_entries.len() {
+                    let (previous, current) = (&self.day_entries[i - 1], &self.day_entries[i]);
+
+                    // we do not want to lose any data, so they need to be exactly the same
+                    let duplicate = previous == current;
+                    if current.datetime - previous.datetime < threshold {
+                        let outcome = if duplicate {
+                            "identical, collapsed"
+                        } else {
+                            "not identical, kept both"
+                        };
+                        explain_pair(explain, previous, current, outcome);
+                    }
+
+                    if duplicate {
+                        self.day_entries[i].id = -1; // mark for deletion
+                    }
+                }
+            }
+            DayEntryComparisonPolicy::Relaxed(options) => {
+                self.collapse_similar_entries(&from_first, options, 0, threshold, explain);
+            }
+            DayEntryComparisonPolicy::Fuzzy { max_distance } => {
+                let options = SimplifyOptions::default();
+                self.collapse_similar_entries(
+                    &from_first,
+                    options,
+                    max_distance,
+                    threshold,
+                    explain,
+                );
             }
         }
 
         self.day_entries.retain(|entry| entry.id != -1);
     }
 
+    /// Collapses the entries closer than `threshold` milliseconds that are similar,
+    /// see [`entry_difference`]. Collapsed entries are marked for deletion.
+    /// Only an entry of the first file and one of the second can be collapsed together,
+    /// as distinct entries of a same file are never duplicates, e.g. two check-ins without notes
+    fn collapse_similar_entries(
+        &mut self,
+        from_first: &[bool],
+        options: SimplifyOptions,
+        max_distance: usize,
+        threshold: i64,
+        explain: &mut dyn FnMut(&str),
+    ) {
+        // each entry is collapsed with at most one entry of the other file
+        let mut matched = vec![false; self.day_entries.len()];
+
+        for i in 1..self.day_entries.len() {
+            // entries are sorted, so we only have to look back until we leave the window
+            let (previous, current) = self.day_entries.split_at_mut(i);
+            let duplicate = &mut current[0];
+
+            for (j, kept) in previous.iter_mut().enumerate().rev() {
+                if duplicate.datetime - kept.datetime >= threshold {
+                    break;
+                }
+                if matched[j] || from_first[j] == from_first[i] {
+                    continue;
+                }
+                if let Some(difference) = entry_difference(kept, duplicate, options, max_distance) {
+                    explain_pair(
+                        explain,
+                        kept,
+                        duplicate,
+                        &format!("{difference}, kept both"),
+                    );
+                    continue;
+                }
+                explain_pair(explain, kept, duplicate, "similar notes, collapsed");
+
+                // keep the longer note, it is likely the most complete one
+                if duplicate.note.len() > kept.note.len() {
+                    kept.note = std::mem::take(&mut duplicate.note);
+                }
+                if duplicate.note_title.len() > kept.note_title.len() {
+                    kept.note_title = std::mem::take(&mut duplicate.note_title);
+                }
+                // photos attached to either side are kept
+                for asset in std::mem::take(&mut duplicate.assets) {
+                    if !kept.assets.contains(&asset) {
+                        kept.assets.push(asset);
+                    }
+                }
+                duplicate.id = -1; // mark for deletion
+                matched[j] = true;
+                matched[i] = true;
+                break;
+            }
+        }
+    }
+
     pub fn sanitize(&mut self) {
         // fix: sometimes custom moods have a custom
         // name and a predefined name
@@ -166,6 +313,24 @@ impl Daylio {
             }
         }
 
+        // tags have to belong to an existing group, so we move dangling ones to the default group
+        if self.tag_groups.is_empty() && !self.tags.is_empty() {
+            self.tag_groups = Daylio::default().tag_groups;
+        }
+        if let Some(default_group) = self.tag_groups.iter().min_by_key(|group| group.order) {
+            let default_group = default_group.id;
+            let group_ids = self
+                .tag_groups
+                .iter()
+                .map(|group| group.id)
+                .collect::<Vec<_>>();
+            for tag in &mut self.tags {
+                if !group_ids.contains(&tag.id_tag_group) {
+                    tag.id_tag_group = default_group;
+                }
+            }
+        }
+
         self.tags.sort_by_key(|x| x.created_at);
         let mut id_generator = IdGenerator::new(1);
         for (i, tag) in self.tags.iter_mut().enumerate() {
@@ -173,8 +338,8 @@ impl Daylio {
             tag.order = i as i64 + 1;
         }
 
-        self.day_entries
-            .sort_by_key(|x| (-x.datetime, -x.year, -x.month));
+        // most recent first
+        self.day_entries.sort_by(|x, y| compare_entries(y, x));
         let mut id_generator = IdGenerator::new(1);
         for entry in &mut self.day_entries {
             entry.id = id_generator.next();
@@ -182,11 +347,192 @@ impl Daylio {
     }
 }
 
+/// Chronological order. Entries at the same time are ordered by content,
+/// so that the order does not depend on the order of the inputs
+fn compare_entries(entry1: &DayEntry, entry2: &DayEntry) -> Ordering {
+    (entry1.datetime, entry1.year, entry1.month, entry1.mood)
+        .cmp(&(entry2.datetime, entry2.year, entry2.month, entry2.mood))
+        .then_with(|| entry1.tags.cmp(&entry2.tags))
+        .then_with(|| entry1.note_title.cmp(&entry2.note_title))
+        .then_with(|| entry1.note.cmp(&entry2.note))
+}
+
+/// Maps a lowercase accented latin letter to its base letter
+fn fold_accent(c: char) -> char {
+    match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => 'a',
+        'ç' | 'ć' | 'č' => 'c',
+        'ď' => 'd',
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => 'e',
+        'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' => 'i',
+        'ł' => 'l',
+        'ñ' | 'ń' | 'ň' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => 'o',
+        'ř' => 'r',
+        'ś' | 'š' | 'ş' => 's',
+        'ť' => 't',
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' => 'u',
+        'ý' | 'ÿ' => 'y',
+        'ź' | 'ż' | 'ž' => 'z',
+        _ => c,
+    }
+}
+
+/// Keeps only the characters that carry meaning, so that notes differing only by
+/// case, whitespace or punctuation compare equal
+fn simplify_note_for_comparing(note: &str, options: SimplifyOptions) -> String {
+    note.chars()
+        .filter(|c| c.is_alphabetic() || (c.is_numeric() && !options.ignore_digits))
+        .flat_map(char::to_lowercase)
+        .map(|c| {
+            if options.fold_accents {
+                fold_accent(c)
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Whether `text1` can be turned into `text2` with at most `max_distance` character edits.
+/// Only the cells near the diagonal are computed, so long notes stay cheap
+fn within_edit_distance(text1: &str, text2: &str, max_distance: usize) -> bool {
+    if text1 == text2 {
+        return true;
+    }
+
+    let chars1 = text1.chars().collect::<Vec<_>>();
+    let chars2 = text2.chars().collect::<Vec<_>>();
+    if chars1.len().abs_diff(chars2.len()) > max_distance {
+        return false;
+    }
+
+    // Levenshtein distance, capped at `over` as the exact value does not matter past the budget
+    let over = max_distance + 1;
+    let mut previous = (0..=chars2.len()).map(|j| j.min(over)).collect::<Vec<_>>();
+    let mut current = vec![over; chars2.len() + 1];
+    for i in 1..=chars1.len() {
+        let from = i.saturating_sub(max_distance).max(1);
+        let to = (i + max_distance).min(chars2.len());
+
+        current[from - 1] = if from == 1 { i.min(over) } else { over };
+        let mut row_min = current[from - 1];
+        for j in from..=to {
+            let substitution = previous[j - 1] + usize::from(chars1[i - 1] != chars2[j - 1]);
+            current[j] = substitution
+                .min(previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(over);
+            row_min = row_min.min(current[j]);
+        }
+        if to < chars2.len() {
+            current[to + 1] = over;
+        }
+
+        // distances never decrease from one row to the next
+        if row_min > max_distance {
+            return false;
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[chars2.len()] <= max_distance
+}
+
+/// Tells why two entries are not similar, or `None` if they are.
+/// Simplified notes and titles can be up to `max_distance` edits apart
+fn entry_difference(
+    entry1: &DayEntry,
+    entry2: &DayEntry,
+    options: SimplifyOptions,
+    max_distance: usize,
+) -> Option<&'static str> {
+    let sorted_tags = |entry: &DayEntry| {
+        let mut tags = entry.tags.clone();
+        tags.sort_unstable();
+        tags
+    };
+    let similar = |note1: &str, note2: &str| {
+        let simplify = |note: &str| simplify_note_for_comparing(note, options);
+        within_edit_distance(&simplify(note1), &simplify(note2), max_distance)
+    };
+
+    if entry1.mood != entry2.mood {
+        Some("different moods")
+    } else if sorted_tags(entry1) != sorted_tags(entry2) {
+        Some("different tags")
+    } else if !similar(&entry1.note_title, &entry2.note_title) {
+        Some("different note titles")
+    } else if !similar(&entry1.note, &entry2.note) {
+        Some("different notes")
+    } else {
+        None
+    }
+}
+
+/// Local date and time of the entry, for explanations
+fn format_entry_date(entry: &DayEntry) -> String {
+    let local = entry.datetime + entry.time_zone_offset;
+    DateTime::from_timestamp_millis(local).map_or_else(
+        || entry.datetime.to_string(),
+        |date| date.format("%Y-%m-%d %H:%M").to_string(),
+    )
+}
+
+fn explain_pair(
+    explain: &mut dyn FnMut(&str),
+    entry1: &DayEntry,
+    entry2: &DayEntry,
+    outcome: &str,
+) {
+    explain(&format!(
+        "{} and {}: {outcome}",
+        format_entry_date(entry1),
+        format_entry_date(entry2)
+    ));
+}
+
+/// Merges two daylio json files into one, only dropping entries that are exactly the same.
+/// See [`merge_with_policy`]
+#[must_use]
+pub fn merge(daylio1: Daylio, daylio2: Daylio) -> Daylio {
+    merge_with_policy(daylio1, daylio2, DayEntryComparisonPolicy::Strict)
+}
+
 /// Merges two daylio json files into one.
 /// We assume the files have version 15, but this is not checked.
-/// We keep everything from the first file, and add the new entries from the other files
+/// We keep everything from the first file, and add the new entries from the other files.
+/// `policy` decides which entries are considered duplicates
+#[must_use]
+pub fn merge_with_policy(
+    daylio1: Daylio,
+    daylio2: Daylio,
+    policy: DayEntryComparisonPolicy,
+) -> Daylio {
+    merge_with_options(daylio1, daylio2, policy, MergeOptions::default())
+}
+
+/// Same as [`merge_with_policy`], with `options` tuning how duplicates are looked for
 #[must_use]
-pub fn merge(mut daylio1: Daylio, mut daylio2: Daylio) -> Daylio {
+pub fn merge_with_options(
+    daylio1: Daylio,
+    daylio2: Daylio,
+    policy: DayEntryComparisonPolicy,
+    options: MergeOptions,
+) -> Daylio {
+    merge_with_explanation(daylio1, daylio2, policy, options, |_| {})
+}
+
+/// Same as [`merge_with_options`], but `explain` is called with a line describing each
+/// comparison of entries closer than the same day threshold, and whether they were collapsed
+#[must_use]
+pub fn merge_with_explanation(
+    mut daylio1: Daylio,
+    mut daylio2: Daylio,
+    policy: DayEntryComparisonPolicy,
+    options: MergeOptions,
+    mut explain: impl FnMut(&str),
+) -> Daylio {
     const BIG_OFFSET: i64 = 1000;
 
     // first_pass: make sure we don't have any duplicates id
@@ -194,20 +540,45 @@ pub fn merge(mut daylio1: Daylio, mut daylio2: Daylio) -> Daylio {
     daylio1.make_ids_distinct(&mut id_generator);
     daylio2.make_ids_distinct(&mut id_generator);
 
+    let first_entries = daylio1.day_entries.len();
     let mut merged = daylio1;
     merged
         .custom_moods
         .append(&mut daylio2.custom_moods.clone());
     merged.tags.append(&mut daylio2.tags.clone());
     merged.day_entries.append(&mut daylio2.day_entries.clone());
+    // entries refer to their photos, so the assets of both files are needed
+    let mut new_assets = 0;
+    for asset in &daylio2.assets {
+        if !merged.assets.contains(asset) {
+            merged.assets.push(asset.clone());
+            new_assets += 1;
+        }
+    }
 
-    merged.remove_duplicates();
+    merged.remove_duplicates(first_entries, policy, options, &mut explain);
     merged.sanitize();
 
     // update metadata
     merged.metadata.number_of_entries = merged.day_entries.len() as i64;
-    merged.metadata.number_of_photos += daylio2.metadata.number_of_photos;
-    merged.metadata.photos_size += daylio2.metadata.photos_size;
+    merged.metadata.number_of_photos = merged.assets.len() as i64;
+    // assets do not tell their size, so the photos added from the second file
+    // are counted with its average photo size
+    if new_assets > 0 {
+        merged.metadata.photos_size +=
+            daylio2.metadata.photos_size * new_assets / daylio2.assets.len() as i64;
+    }
 
     merged
 }
+
+/// Returns the index of the backup with the most entries, the first one on ties.
+/// Using it as the merge reference keeps the richest data and minimizes insertions
+#[must_use]
+pub fn largest_daylio(daylios: &[Daylio]) -> Option<usize> {
+    daylios
+        .iter()
+        .enumerate()
+        .max_by_key(|(i, daylio)| (daylio.day_entries.len(), std::cmp::Reverse(*i)))
+        .map(|(i, _)| i)
+}
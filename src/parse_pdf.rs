@@ -6,23 +6,26 @@ use std::mem;
 use std::path::Path;
 
 use chrono::NaiveDate;
-use color_eyre::eyre::{ContextCompat, WrapErr};
+use color_eyre::eyre::{eyre, ContextCompat, WrapErr};
 use color_eyre::Result;
-use nom::{Finish, Parser};
 use nom::branch::alt;
 use nom::bytes::complete::{take_till, take_until};
 use nom::character::complete::{digit1, line_ending, multispace0, one_of, space0};
 use nom::combinator::{eof, map, map_res};
 use nom::multi::{count, many_till};
 use nom::sequence::{delimited, preceded, terminated, tuple};
+use nom::{Finish, Parser};
 use pdftotext::pdftotext_layout;
+use serde::Serializer;
+use serde_derive::Serialize;
 
 type IResult<I, O> = nom::IResult<I, O, nom::error::VerboseError<I>>;
 
-#[derive(Clone, Debug, PartialEq)]
-pub(crate) struct StatLine {
-    pub(crate) name: String,
-    pub(crate) count: u32,
+/// A line of the stats on the first page: how many times a mood or a tag was used
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct StatLine {
+    pub name: String,
+    pub count: u32,
 }
 
 impl StatLine {
@@ -31,21 +34,34 @@ impl StatLine {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
-pub(crate) struct DayEntry {
-    pub(crate) date: NaiveDate,
-    pub(crate) day_hour: String,
-    pub(crate) mood: String,
-    pub(crate) note: Vec<String>,
+/// A day entry as written in the PDF, before tags are told apart from the note
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ParsedDayEntry {
+    #[serde(serialize_with = "serialize_date")]
+    pub date: NaiveDate,
+    /// Weekday and time, e.g. "Sunday 8 53 PM"
+    pub day_hour: String,
+    pub mood: String,
+    /// Lines below the date, tags and note mixed
+    pub note: Vec<String>,
+}
+
+/// A PDF export as parsed, before it is interpreted as a Daylio backup
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ParsedPdf {
+    pub stats: Vec<StatLine>,
+    pub day_entries: Vec<ParsedDayEntry>,
+    /// Parts of the text skipped in recovery mode, see [`parse_text`]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
 }
 
-#[derive(Clone, Debug, PartialEq)]
-pub(crate) struct ParsedPdf {
-    pub(crate) stats: Vec<StatLine>,
-    pub(crate) day_entries: Vec<DayEntry>,
+/// Writes dates as YYYY-MM-DD, without needing chrono's serde support
+fn serialize_date<S: Serializer>(date: &NaiveDate, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(date)
 }
 
-fn extract_txt(pdf: &Path) -> Result<String> {
+pub(crate) fn extract_txt(pdf: &Path) -> Result<String> {
     let path = pdf.to_str().wrap_err("Invalid path")?;
     let txt = pdftotext_layout(path)?;
 
@@ -85,53 +101,142 @@ fn parse_stat_lines(input: &str) -> IResult<&str, Vec<StatLine>> {
     )(input)
 }
 
-/// differences between english and french:
-/// - month names
-/// - "month day, year" becomes "day month year" in french
-/// - 24 hour clock
-fn convert_french_date(date: &str) -> Option<String> {
-    let date = date.to_lowercase();
-    let month_dict = [
-        ("janvier", "january"),
-        ("février", "february"),
-        ("mars", "march"),
-        ("avril", "april"),
-        ("mai", "may"),
-        ("juin", "june"),
-        ("juillet", "july"),
-        ("août", "august"),
-        ("septembre", "september"),
-        ("octobre", "october"),
-        ("novembre", "november"),
-        ("décembre", "december"),
+/// Languages of the PDF export, as far as dates are concerned
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Language {
+    English,
+    French,
+    German,
+    Spanish,
+}
+
+impl Language {
+    const ALL: [Language; 4] = [
+        Language::English,
+        Language::French,
+        Language::German,
+        Language::Spanish,
     ];
 
-    if !month_dict.iter().any(|(french, _)| date.contains(french)) {
-        return Some(date); // not a french date
+    /// Lowercase month names, from January to December
+    fn months(self) -> [&'static str; 12] {
+        match self {
+            Language::English => [
+                "january",
+                "february",
+                "march",
+                "april",
+                "may",
+                "june",
+                "july",
+                "august",
+                "september",
+                "october",
+                "november",
+                "december",
+            ],
+            Language::French => [
+                "janvier",
+                "février",
+                "mars",
+                "avril",
+                "mai",
+                "juin",
+                "juillet",
+                "août",
+                "septembre",
+                "octobre",
+                "novembre",
+                "décembre",
+            ],
+            Language::German => [
+                "januar",
+                "februar",
+                "märz",
+                "april",
+                "mai",
+                "juni",
+                "juli",
+                "august",
+                "september",
+                "oktober",
+                "november",
+                "dezember",
+            ],
+            Language::Spanish => [
+                "enero",
+                "febrero",
+                "marzo",
+                "abril",
+                "mayo",
+                "junio",
+                "julio",
+                "agosto",
+                "septiembre",
+                "octubre",
+                "noviembre",
+                "diciembre",
+            ],
+        }
+    }
+
+    /// How a date is written, once its month name is replaced by the month number
+    fn date_format(self) -> &'static str {
+        match self {
+            Language::English => "%m %d, %Y",
+            Language::French => "%d %m %Y",
+            Language::German => "%d. %m %Y",
+            Language::Spanish => "%d de %m de %Y",
+        }
     }
 
-    let mut date_parts = date.split_whitespace();
-    let day = date_parts.next()?;
+    /// Parses the lowercase `date`, if it is written in this language
+    fn parse_date(self, date: &str) -> Option<NaiveDate> {
+        let (word, month) = date_words(date).find_map(|word| Some((word, self.month(word)?)))?;
 
-    let en_month = date_parts.next()?;
-    let month = month_dict
-        .iter()
-        .find(|(french, _)| *french == en_month)
-        .map(|(_, english)| english)?;
+        let start = date.find(word)?;
+        let rest = &date[start + word.len()..];
+        // abbreviated month names may end with a dot, as in "févr."
+        let rest = rest.strip_prefix('.').unwrap_or(rest);
+        let date = format!("{}{month}{rest}", &date[..start]);
 
-    let year = date_parts.next()?;
+        NaiveDate::parse_from_str(&date, self.date_format()).ok()
+    }
 
-    Some(format!("{month} {day}, {year}"))
+    /// 1-based month number. Abbreviations such as "aug" or "févr" are accepted
+    /// as long as they have at least 3 letters and only match one month
+    fn month(self, word: &str) -> Option<u32> {
+        let months = (1..).zip(self.months());
+
+        if let Some((i, _)) = months.clone().find(|(_, month)| *month == word) {
+            return Some(i);
+        }
+        if word.chars().count() < 3 {
+            return None;
+        }
+
+        let mut abbreviated = months.filter(|(_, month)| month.starts_with(word));
+        match (abbreviated.next(), abbreviated.next()) {
+            (Some((i, _)), None) => Some(i),
+            _ => None,
+        }
+    }
 }
 
-fn convert_language_date(date: &str) -> Option<String> {
-    convert_french_date(date)
+fn date_words(date: &str) -> impl Iterator<Item = &str> {
+    date.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
 }
 
-/// Date looks like August 2, 2022
+/// Date looks like "August 2, 2022", "2 août 2022", "2. August 2022" or "2 de agosto de 2022".
+/// Some month names are shared between languages, so each language is tried in turn
 fn string_to_date(date: &str) -> Result<NaiveDate> {
-    let date = convert_language_date(date).wrap_err("Invalid date")?;
-    NaiveDate::parse_from_str(&date, "%B %d, %Y").wrap_err(format!("Invalid date: {date}"))
+    let lowercase = date.trim().to_lowercase();
+
+    Language::ALL
+        .into_iter()
+        .find_map(|language| language.parse_date(&lowercase))
+        .wrap_err_with(|| format!("Invalid date: {date}"))
 }
 
 fn parse_date(input: &str) -> IResult<&str, NaiveDate> {
@@ -191,7 +296,12 @@ fn parse_note_body(input: &str) -> IResult<&str, (Vec<&str>, Option<NaiveDate>)>
 /// date {2, n}mood\nday hour\n(\n\n|\n{0, 1}([^\n]{1, n}, \n){1, n}\n{2, 3})
 /// ```
 /// body can also be ended by `\nEOF`
-fn parse_day_entries(input: &str) -> IResult<&str, Vec<DayEntry>> {
+///
+/// `progress` is called on each day entry as soon as it is parsed
+fn parse_day_entries<'a>(
+    input: &'a str,
+    progress: &mut dyn FnMut(&ParsedDayEntry),
+) -> IResult<&'a str, Vec<ParsedDayEntry>> {
     // So, we are in some kind of weird situation here.
     // We use the date as a separator, as it is the only thing that is guaranteed to be there.
     // But the date is the first thing we parse, so we're gonna be off by one.
@@ -205,12 +315,15 @@ fn parse_day_entries(input: &str) -> IResult<&str, Vec<DayEntry>> {
 
             let note = note.into_iter().map(ToOwned::to_owned).collect();
 
-            Some(DayEntry {
+            let entry = ParsedDayEntry {
                 date: mem::replace(&mut prev_date, next_date).unwrap(),
                 mood: mood.to_owned(),
                 day_hour: day_hour.to_owned(),
                 note,
-            })
+            };
+            progress(&entry);
+
+            Some(entry)
         },
     );
 
@@ -218,6 +331,65 @@ fn parse_day_entries(input: &str) -> IResult<&str, Vec<DayEntry>> {
     res.map(|(input, days)| (input, days.into_iter().flatten().collect()))
 }
 
+/// Splits the day entries before each line starting with a date, the same separator as
+/// [`parse_day_entries`], so that each part can be parsed on its own.
+/// Returns the parts with their offset in `input`
+fn split_day_entries(input: &str) -> Vec<(usize, &str)> {
+    let mut starts = vec![0];
+    let mut offset = 0;
+    for line in input.split_inclusive('\n') {
+        if offset != 0 && parse_date(&input[offset..]).is_ok() {
+            starts.push(offset);
+        }
+        offset += line.len();
+    }
+    starts.push(input.len());
+
+    starts
+        .windows(2)
+        .map(|bounds| (bounds[0], &input[bounds[0]..bounds[1]]))
+        .collect()
+}
+
+/// Parses the day entries one at a time, skipping the ones not matching the grammar.
+/// `text` is the whole text, used to locate the skipped parts in the warnings
+fn parse_day_entries_recovering(
+    text: &str,
+    input: &str,
+    progress: &mut dyn FnMut(&ParsedDayEntry),
+) -> (Vec<ParsedDayEntry>, Vec<String>) {
+    let input_offset = text.len() - input.len();
+
+    let mut day_entries = Vec::new();
+    let mut warnings = Vec::new();
+    for (offset, part) in split_day_entries(input) {
+        let first = text[..input_offset + offset].matches('\n').count() + 1;
+        let last = first + part.trim_end().matches('\n').count();
+
+        match parse_day_entries(part, progress).finish() {
+            Ok((_, entries)) => {
+                // the day and hour line matches anything, so it is only checked here
+                for entry in entries {
+                    match crate::analyze_pdf::parse_date(&entry) {
+                        Ok(_) => day_entries.push(entry),
+                        Err(error) => warnings.push(format!(
+                            "Skipped lines {first} to {last}: \"{}\", {error}",
+                            entry.day_hour
+                        )),
+                    }
+                }
+            }
+            Err(_) if part.trim().is_empty() => {}
+            Err(_) => {
+                let snippet = part.lines().next().unwrap_or_default().trim_end();
+                warnings.push(format!("Skipped lines {first} to {last}: \"{snippet}\""));
+            }
+        }
+    }
+
+    (day_entries, warnings)
+}
+
 fn parse_page_number(input: &str) -> IResult<&str, &str> {
     delimited(space0, digit1, line_ending)(input)
 }
@@ -225,33 +397,104 @@ fn parse_page_number(input: &str) -> IResult<&str, &str> {
 #[derive(Debug, Clone)]
 struct ParsePdfError {
     json: String,
+    /// 1-based position of the failure in the extracted text
+    line: usize,
+    column: usize,
+    /// Text starting at the failure, up to the end of its line
+    snippet: String,
+}
+
+impl ParsePdfError {
+    fn new(input: &str, error: nom::error::VerboseError<&str>) -> Self {
+        // the first error is the innermost one, so the closest to the actual problem
+        let remaining = error.errors.first().map_or("", |(remaining, _)| *remaining);
+        let offset = input.len() - remaining.len();
+
+        let line_start = input[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line = input[..offset].matches('\n').count() + 1;
+        let column = input[line_start..offset].chars().count() + 1;
+        let snippet = remaining
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .trim_end()
+            .chars()
+            .take(80)
+            .collect();
+
+        Self {
+            json: nom::error::convert_error(input, error),
+            line,
+            column,
+            snippet,
+        }
+    }
 }
 
 impl Display for ParsePdfError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Failed to parse PDF:\n{}", self.json)
+        write!(
+            f,
+            "Failed to parse PDF at line {}, column {}: \"{}\"\n{}",
+            self.line, self.column, self.snippet, self.json
+        )
     }
 }
 
 impl std::error::Error for ParsePdfError {}
 
-pub(crate) fn parse_pdf(path: &Path) -> Result<ParsedPdf> {
-    let text = extract_txt(path)?;
-    let input = text.as_str();
+/// Parses a PDF export without interpreting it, which helps debugging.
+/// Use [`crate::load_daylio_pdf`] to get a Daylio backup
+pub fn parse_pdf(path: &Path) -> Result<ParsedPdf> {
+    parse_pdf_with_progress(path, |_| {})
+}
 
-    let first_page = preceded(parse_header, parse_stat_lines);
+/// Same as [`parse_pdf`], but `progress` is called on each day entry as soon as it is parsed.
+/// Big PDFs can take a while, this allows showing some feedback
+pub(crate) fn parse_pdf_with_progress(
+    path: &Path,
+    mut progress: impl FnMut(&ParsedDayEntry),
+) -> Result<ParsedPdf> {
+    let text = extract_txt(path)?;
+    parse_text(&text, false, progress)
+}
 
-    let mut parser = tuple((first_page, parse_day_entries));
+/// Parses text extracted from a PDF, see [`parse_pdf_with_progress`].
+/// With `recover`, day entries not matching the grammar are skipped instead of failing
+/// the whole document. The skipped parts are listed in [`ParsedPdf::warnings`]
+pub(crate) fn parse_text(
+    input: &str,
+    recover: bool,
+    mut progress: impl FnMut(&ParsedDayEntry),
+) -> Result<ParsedPdf> {
+    let mut first_page = preceded(parse_header, parse_stat_lines);
+
+    if recover {
+        // the first page holds the stats, there is no entry to skip to
+        let (entries, stats) = first_page(input)
+            .finish()
+            .map_err(|e| ParsePdfError::new(input, e))?;
+        let (day_entries, warnings) = parse_day_entries_recovering(input, entries, &mut progress);
+
+        return Ok(ParsedPdf {
+            stats,
+            day_entries,
+            warnings,
+        });
+    }
 
-    parser(input)
+    first_page(input)
+        .and_then(|(input, stats)| {
+            let (input, day_entries) = parse_day_entries(input, &mut progress)?;
+            Ok((input, (stats, day_entries)))
+        })
         .finish()
-        .map(|(_, (stats, day_entries))| ParsedPdf { stats, day_entries })
-        .map_err(|e| {
-            ParsePdfError {
-                json: nom::error::convert_error(input, e),
-            }
-            .into()
+        .map(|(_, (stats, day_entries))| ParsedPdf {
+            stats,
+            day_entries,
+            warnings: vec![],
         })
+        .map_err(|e| ParsePdfError::new(input, e).into())
 }
 
 #[cfg(test)]
@@ -322,12 +565,12 @@ pub(crate) mod tests {
                 },
             ],
             day_entries: vec![
-                DayEntry {
+                ParsedDayEntry {
                     date: string_to_date("January 24, 2023").unwrap(),
                     day_hour: "Tuesday 11 36 AM".to_owned(),
                     mood: "AWFUL".to_owned(),
                     note: vec![],
-                }, DayEntry {
+                }, ParsedDayEntry {
                     date: string_to_date("January 24, 2023").unwrap(),
                     day_hour: "Tuesday 9 59 AM".to_owned(),
                     mood: "RAD".to_owned(),
@@ -337,7 +580,7 @@ pub(crate) mod tests {
                         "Note body".to_owned(),
                     ],
                 },
-                DayEntry {
+                ParsedDayEntry {
                     date: string_to_date("January 11, 2023").unwrap(),
                     day_hour: "Wednesday 10 20 PM".to_owned(),
                     mood: "MEH".to_owned(),
@@ -348,7 +591,7 @@ pub(crate) mod tests {
                         "Because I love breaking parsers".to_owned(),
                     ],
                 },
-                DayEntry {
+                ParsedDayEntry {
                     date: string_to_date("January 4, 2023").unwrap(),
                     day_hour: "Wednesday 8 00 PM".to_owned(),
                     mood: "AWFUL".to_owned(),
@@ -356,7 +599,7 @@ pub(crate) mod tests {
                         "manger sain        films       ménage          shopping".to_owned(),
                     ],
                 },
-                DayEntry {
+                ParsedDayEntry {
                     date: string_to_date("May 16, 2015").unwrap(),
                     day_hour: "Saturday 8 00 PM".to_string(),
                     mood: "NULL".to_string(),
@@ -374,6 +617,7 @@ pub(crate) mod tests {
                     ],
                 },
             ],
+            warnings: vec![],
         };
 
         assert_eq!(actual, expected);
@@ -471,223 +715,352 @@ pub(crate) mod tests {
         assert_eq!(parsed.1, expected_parsed);
     }
 
+    #[test]
+    fn test_string_to_date_languages() {
+        let expected = NaiveDate::from_ymd_opt(2023, 1, 2).unwrap();
+
+        assert_eq!(string_to_date("January 2, 2023").unwrap(), expected);
+        assert_eq!(string_to_date("2 janvier 2023").unwrap(), expected);
+        assert_eq!(string_to_date("2. Januar 2023").unwrap(), expected);
+        assert_eq!(string_to_date("2 de enero de 2023").unwrap(), expected);
+
+        let march = NaiveDate::from_ymd_opt(2022, 3, 15).unwrap();
+        assert_eq!(string_to_date("15. März 2022").unwrap(), march);
+        assert_eq!(string_to_date("15 de marzo de 2022").unwrap(), march);
+
+        // abbreviated month names, as accepted by chrono
+        assert_eq!(string_to_date("Jan 2, 2023").unwrap(), expected);
+        assert_eq!(string_to_date("Mar 15, 2022").unwrap(), march);
+        assert_eq!(string_to_date("15. Mär 2022").unwrap(), march);
+        assert_eq!(
+            string_to_date("2 févr. 2023").unwrap(),
+            NaiveDate::from_ymd_opt(2023, 2, 2).unwrap()
+        );
+        // "jui" could be juin or juillet
+        assert!(string_to_date("2 jui 2023").is_err());
+        // also English abbreviations, so the whole date decides the language
+        assert_eq!(string_to_date("15 de mar de 2022").unwrap(), march);
+        assert_eq!(
+            string_to_date("2 de jun de 2023").unwrap(),
+            NaiveDate::from_ymd_opt(2023, 6, 2).unwrap()
+        );
+        assert_eq!(
+            string_to_date("2 de jul de 2023").unwrap(),
+            NaiveDate::from_ymd_opt(2023, 7, 2).unwrap()
+        );
+
+        // English dates have a comma after the day
+        assert!(string_to_date("January 2 2023").is_err());
+
+        assert!(string_to_date("Note about January").is_err());
+        assert!(string_to_date("31 février 2023").is_err());
+    }
+
+    #[test]
+    fn test_parse_error_position() {
+        let text = "Daylio Export\n\
+                    April 27, 2022 - January 23, 2023\n\n\n\n\
+                    \x20    rad       15×\n\n\n\n\
+                    Not a date        RAD\n\
+                    Tuesday 11 00 PM\n";
+
+        let error = parse_text(text, false, |_| {}).unwrap_err();
+        let error = error.downcast_ref::<ParsePdfError>().unwrap();
+
+        assert_eq!((error.line, error.column), (10, 1));
+        assert_eq!(error.snippet, "Not a date        RAD");
+    }
+
+    #[test]
+    fn test_parse_recovering() -> Result<()> {
+        let text = "Daylio Export\n\
+                    April 27, 2022 - January 23, 2023\n\n\n\n\
+                    \x20    rad       15×\n\n\n\n\
+                    Not a date        RAD\n\
+                    Tuesday 11 00 PM\n\
+                    August 2, 2022        RAD\n\
+                    Tuesday 10 00 PM\n\
+                    Note A\n\
+                    August 1, 2022        MEH\n\
+                    July 31, 2022        AWFUL\n\
+                    Sunday 4 00 PM\n\
+                    Note C\n";
+
+        assert!(parse_text(text, false, |_| {}).is_err());
+
+        let parsed = parse_text(text, true, |_| {})?;
+
+        let dates = parsed
+            .day_entries
+            .iter()
+            .map(|entry| entry.date)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            dates,
+            [
+                string_to_date("August 2, 2022")?,
+                string_to_date("July 31, 2022")?
+            ]
+        );
+        assert_eq!(parsed.day_entries[1].note, ["Note C"]);
+        assert_eq!(
+            parsed.warnings,
+            [
+                "Skipped lines 10 to 11: \"Not a date        RAD\"",
+                "Skipped lines 15 to 15: \"August 1, 2022        MEH\""
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_recovering_invalid_time() -> Result<()> {
+        let text = "Daylio Export\n\
+                    April 27, 2022 - January 23, 2023\n\n\n\n\
+                    \x20    rad       15×\n\n\n\n\
+                    August 3, 2022        RAD\n\
+                    Someday at noon\n\
+                    Note A\n\
+                    August 2, 2022        RAD\n\
+                    Tuesday 10 00 PM\n\
+                    Note B\n";
+
+        let parsed = parse_text(text, true, |_| {})?;
+
+        assert_eq!(parsed.day_entries.len(), 1);
+        assert_eq!(parsed.day_entries[0].note, ["Note B"]);
+        assert_eq!(parsed.warnings.len(), 1);
+        assert!(parsed.warnings[0].starts_with("Skipped lines 10 to 12: \"Someday at noon\""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_pdf_progress() {
+        let mut calls = 0;
+        let parsed = parse_pdf_with_progress(Path::new(TEST_PDF), |_| calls += 1).unwrap();
+
+        assert_eq!(calls, parsed.day_entries.len());
+    }
+
     #[test]
     fn test_parse_pdf() {
         let parsed = parse_pdf(Path::new(TEST_PDF)).unwrap();
         let expected_tags = expected_parsed_tags();
 
         let expected_entries = vec![
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("August 2, 2022").unwrap(),
                 day_hour: "Tuesday 11 00 PM".to_owned(),
                 mood: "MOOD 2 VUP".to_owned(),
                 note: vec!["Note title 0 LKH".to_owned(), "Note 0 LHF".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("August 2, 2022").unwrap(),
                 day_hour: "Tuesday 6 00 PM".to_owned(),
                 mood: "RAD".to_owned(),
                 note: vec!["Note title 1 OAK".to_owned(), "Note 1 QJO".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("August 1, 2022").unwrap(),
                 day_hour: "Monday 8 45 PM".to_owned(),
                 mood: "RAD".to_owned(),
                 note: vec!["Note title 2 FFU".to_owned(), "Note 2 JBQ".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("August 1, 2022").unwrap(),
                 day_hour: "Monday 10 30 AM".to_owned(),
                 mood: "MOOD 2 VUP".to_owned(),
                 note: vec!["Note title 3 MKL".to_owned(), "Note 3 VPH".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("July 31, 2022").unwrap(),
                 day_hour: "Sunday 4 00 PM".to_owned(),
                 mood: "MOOD 2 VUP".to_owned(),
                 note: vec!["Note title 4 BTD".to_owned(), "Note 4 UDK".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("July 30, 2022").unwrap(),
                 day_hour: "Saturday 9 00 AM".to_owned(),
                 mood: "MOOD 1 QBL".to_owned(),
                 note: vec!["Note title 5 VXG".to_owned(), "Note 5 AOT".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("July 29, 2022").unwrap(),
                 day_hour: "Friday 8 00 AM".to_owned(),
                 mood: "MOOD 2 VUP".to_owned(),
                 note: vec!["Note title 6 JIG".to_owned(), "Note 6 GVX".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("July 25, 2022").unwrap(),
                 day_hour: "Monday 10 01 AM".to_owned(),
                 mood: "RAD".to_owned(),
                 note: vec!["Note title 7 IFI".to_owned(), "Note 7 ABH".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("July 23, 2022").unwrap(),
                 day_hour: "Saturday 10 58 AM".to_owned(),
                 mood: "MOOD 1 QBL".to_owned(),
                 note: vec!["Note title 8 AGV".to_owned(), "Note 8 UGW".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("July 23, 2022").unwrap(),
                 day_hour: "Saturday 9 01 AM".to_owned(),
                 mood: "MOOD 1 QBL".to_owned(),
                 note: vec!["Note title 9 VGL".to_owned(), "Note 9 XMI".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("July 23, 2022").unwrap(),
                 day_hour: "Saturday 7 44 AM".to_owned(),
                 mood: "MEH".to_owned(),
                 note: vec!["Note title 10 YIG".to_owned(), "Note 10 ADT".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("July 23, 2022").unwrap(),
                 day_hour: "Saturday 7 26 AM".to_owned(),
                 mood: "MOOD 1 QBL".to_owned(),
                 note: vec!["Note title 11 FSE".to_owned(), "Note 11 GUP".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("July 1, 2022").unwrap(),
                 day_hour: "Friday 9 19 PM".to_owned(),
                 mood: "MOOD 1 QBL".to_owned(),
                 note: vec!["Note title 12 LGC".to_owned(), "Note 12 XKN".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("June 30, 2022").unwrap(),
                 day_hour: "Thursday 6 39 AM".to_owned(),
                 mood: "GOOD".to_owned(),
                 note: vec!["Note title 13 AKM".to_owned(), "Note 13 YJP".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("June 26, 2022").unwrap(),
                 day_hour: "Sunday 5 00 PM".to_owned(),
                 mood: "RAD".to_owned(),
                 note: vec!["Note title 14 CGY".to_owned(), "Note 14 XHV".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("June 23, 2022").unwrap(),
                 day_hour: "Thursday 12 52 PM".to_owned(),
                 mood: "GOOD".to_owned(),
                 note: vec!["Note title 15 IQK".to_owned(), "Note 15 JJD".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("June 23, 2022").unwrap(),
                 day_hour: "Thursday 12 05 PM".to_owned(),
                 mood: "MOOD 1 QBL".to_owned(),
                 note: vec!["Note title 16 RDS".to_owned(), "Note 16 TYC".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("June 23, 2022").unwrap(),
                 day_hour: "Thursday 8 04 AM".to_owned(),
                 mood: "GOOD".to_owned(),
                 note: vec!["Note title 17 MCA".to_owned(), "Note 17 FGP".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("June 22, 2022").unwrap(),
                 day_hour: "Wednesday 6 00 PM".to_owned(),
                 mood: "GOOD".to_owned(),
                 note: vec!["Note title 18 BFC".to_owned(), "Note 18 VLP".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("June 20, 2022").unwrap(),
                 day_hour: "Monday 9 00 AM".to_owned(),
                 mood: "MOOD 1 QBL".to_owned(),
                 note: vec!["Note title 19 OVK".to_owned(), "Note 19 BIB".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("June 19, 2022").unwrap(),
                 day_hour: "Sunday 9 29 PM".to_owned(),
                 mood: "MOOD 2 VUP".to_owned(),
                 note: vec!["Note title 20 IJG".to_owned(), "Note 20 JWW".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("June 18, 2022").unwrap(),
                 day_hour: "Saturday 9 29 PM".to_owned(),
                 mood: "RAD".to_owned(),
                 note: vec!["Note title 21 YYM".to_owned(), "Note 21 LGX".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("June 13, 2022").unwrap(),
                 day_hour: "Monday 9 25 PM".to_owned(),
                 mood: "GOOD".to_owned(),
                 note: vec!["Note title 22 DDS".to_owned(), "Note 22 PDV".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("June 11, 2022").unwrap(),
                 day_hour: "Saturday 10 00 AM".to_owned(),
                 mood: "RAD".to_owned(),
                 note: vec!["Note title 23 HWK".to_owned(), "Note 23 IXE".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("June 9, 2022").unwrap(),
                 day_hour: "Thursday 9 14 PM".to_owned(),
                 mood: "RAD".to_owned(),
                 note: vec!["Note title 24 EXK".to_owned(), "Note 24 NHO".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("June 9, 2022").unwrap(),
                 day_hour: "Thursday 10 21 AM".to_owned(),
                 mood: "MOOD 1 QBL".to_owned(),
                 note: vec!["Note title 25 HVQ".to_owned(), "Note 25 KLA".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("June 6, 2022").unwrap(),
                 day_hour: "Monday 8 50 PM".to_owned(),
                 mood: "RAD".to_owned(),
                 note: vec!["Note title 26 ONQ".to_owned(), "Note 26 DCC".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("June 4, 2022").unwrap(),
                 day_hour: "Saturday 9 50 PM".to_owned(),
                 mood: "MOOD 0 KWY".to_owned(),
                 note: vec!["Note title 27 PBF".to_owned(), "Note 27 BGL".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("June 3, 2022").unwrap(),
                 day_hour: "Friday 10 24 AM".to_owned(),
                 mood: "MOOD 0 KWY".to_owned(),
                 note: vec!["Note title 28 FGA".to_owned(), "Note 28 AEQ".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("May 29, 2022").unwrap(),
                 day_hour: "Sunday 8 42 PM".to_owned(),
                 mood: "RAD".to_owned(),
                 note: vec!["Note title 29 AIU".to_owned(), "Note 29 GVL".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("May 28, 2022").unwrap(),
                 day_hour: "Saturday 6 00 PM".to_owned(),
                 mood: "RAD".to_owned(),
                 note: vec!["Note title 30 RRM".to_owned(), "Note 30 QVS".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("May 27, 2022").unwrap(),
                 day_hour: "Friday 8 42 PM".to_owned(),
                 mood: "GOOD".to_owned(),
                 note: vec!["Note title 31 LPS".to_owned(), "Note 31 HKU".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("May 26, 2022").unwrap(),
                 day_hour: "Thursday 8 00 PM".to_owned(),
                 mood: "GOOD".to_owned(),
                 note: vec!["Note title 32 MGE".to_owned(), "Note 32 PRG".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("May 25, 2022").unwrap(),
                 day_hour: "Wednesday 4 55 AM".to_owned(),
                 mood: "MOOD 1 QBL".to_owned(),
                 note: vec!["Note title 33 AMR".to_owned(), "Note 33 MYX".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("May 24, 2022").unwrap(),
                 day_hour: "Tuesday 8 44 PM".to_owned(),
                 mood: "MOOD 2 VUP".to_owned(),
                 note: vec!["Note title 34 YRH".to_owned(), "Note 34 SXS".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("May 22, 2022").unwrap(),
                 day_hour: "Sunday 8 53 PM".to_owned(),
                 mood: "RAD".to_owned(),
@@ -697,7 +1070,7 @@ pub(crate) mod tests {
                     "Note 35 AHM".to_owned(),
                 ],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("May 20, 2022").unwrap(),
                 day_hour: "Friday 8 15 PM".to_owned(),
                 mood: "MOOD 0 KWY".to_owned(),
@@ -707,13 +1080,13 @@ pub(crate) mod tests {
                     "Note 36 AFX".to_owned(),
                 ],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("May 20, 2022").unwrap(),
                 day_hour: "Friday 5 11 AM".to_owned(),
                 mood: "GOOD".to_owned(),
                 note: vec!["Note title 37 SHL".to_owned(), "Note 37 YKU".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("May 15, 2022").unwrap(),
                 day_hour: "Sunday 9 00 PM".to_owned(),
                 mood: "GOOD".to_owned(),
@@ -724,7 +1097,7 @@ pub(crate) mod tests {
                     "Note 38 HPJ".to_owned(),
                 ],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("May 14, 2022").unwrap(),
                 day_hour: "Saturday 1 50 PM".to_owned(),
                 mood: "GOOD".to_owned(),
@@ -734,7 +1107,7 @@ pub(crate) mod tests {
                     "Note 39 KFO".to_owned(),
                 ],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("May 13, 2022").unwrap(),
                 day_hour: "Friday 6 00 PM".to_owned(),
                 mood: "GOOD".to_owned(),
@@ -744,19 +1117,19 @@ pub(crate) mod tests {
                     "Note 40 DBV".to_owned(),
                 ],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("May 12, 2022").unwrap(),
                 day_hour: "Thursday 7 04 AM".to_owned(),
                 mood: "BAD".to_owned(),
                 note: vec!["Note title 41 EBK".to_owned(), "Note 41 HVI".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("May 11, 2022").unwrap(),
                 day_hour: "Wednesday 11 17 AM".to_owned(),
                 mood: "GOOD".to_owned(),
                 note: vec!["Note title 42 OLY".to_owned(), "Note 42 FQU".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("May 11, 2022").unwrap(),
                 day_hour: "Wednesday 9 39 AM".to_owned(),
                 mood: "BAD".to_owned(),
@@ -766,7 +1139,7 @@ pub(crate) mod tests {
                     "Note 43 MBW".to_owned(),
                 ],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("May 10, 2022").unwrap(),
                 day_hour: "Tuesday 9 57 AM".to_owned(),
                 mood: "MOOD 2 VUP".to_owned(),
@@ -776,7 +1149,7 @@ pub(crate) mod tests {
                     "Note 44 BIV".to_owned(),
                 ],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("May 9, 2022").unwrap(),
                 day_hour: "Monday 8 00 PM".to_owned(),
                 mood: "GOOD".to_owned(),
@@ -786,7 +1159,7 @@ pub(crate) mod tests {
                     "Note 45 OUF".to_owned(),
                 ],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("May 8, 2022").unwrap(),
                 day_hour: "Sunday 8 27 PM".to_owned(),
                 mood: "RAD".to_owned(),
@@ -797,7 +1170,7 @@ pub(crate) mod tests {
                     "Note 46 FWU".to_owned(),
                 ],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("May 7, 2022").unwrap(),
                 day_hour: "Saturday 7 00 PM".to_owned(),
                 mood: "RAD".to_owned(),
@@ -807,7 +1180,7 @@ pub(crate) mod tests {
                     "Note 47 AND".to_owned(),
                 ],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("May 6, 2022").unwrap(),
                 day_hour: "Friday 5 00 PM".to_owned(),
                 mood: "GOOD".to_owned(),
@@ -817,7 +1190,7 @@ pub(crate) mod tests {
                     "Note 48 NNJ".to_owned(),
                 ],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("May 5, 2022").unwrap(),
                 day_hour: "Thursday 8 37 PM".to_owned(),
                 mood: "GOOD".to_owned(),
@@ -827,7 +1200,7 @@ pub(crate) mod tests {
                     "Note 49 AFH".to_owned(),
                 ],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("May 4, 2022").unwrap(),
                 day_hour: "Wednesday 8 45 PM".to_owned(),
                 mood: "RAD".to_owned(),
@@ -837,7 +1210,7 @@ pub(crate) mod tests {
                     "Note 50 USB".to_owned(),
                 ],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("May 3, 2022").unwrap(),
                 day_hour: "Tuesday 6 31 PM".to_owned(),
                 mood: "GOOD".to_owned(),
@@ -847,7 +1220,7 @@ pub(crate) mod tests {
                     "Note 51 DMN".to_owned(),
                 ],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("May 2, 2022").unwrap(),
                 day_hour: "Monday 8 00 PM".to_owned(),
                 mood: "GOOD".to_owned(),
@@ -857,7 +1230,7 @@ pub(crate) mod tests {
                     "Note 52 VUF".to_owned(),
                 ],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("May 2, 2022").unwrap(),
                 day_hour: "Monday 5 12 PM".to_owned(),
                 mood: "MOOD 2 VUP".to_owned(),
@@ -867,7 +1240,7 @@ pub(crate) mod tests {
                     "Note 53 NTR".to_owned(),
                 ],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("May 1, 2022").unwrap(),
                 day_hour: "Sunday 3 19 PM".to_owned(),
                 mood: "GOOD".to_owned(),
@@ -878,13 +1251,13 @@ pub(crate) mod tests {
                     "Note 54 HOI".to_owned(),
                 ],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("April 30, 2022").unwrap(),
                 day_hour: "Saturday 1 30 PM".to_owned(),
                 mood: "RAD".to_owned(),
                 note: vec!["Note title 55 NWO".to_owned(), "Note 55 JGI".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("April 30, 2022").unwrap(),
                 day_hour: "Saturday 6 09 AM".to_owned(),
                 mood: "MOOD 1 QBL".to_owned(),
@@ -894,7 +1267,7 @@ pub(crate) mod tests {
                     "Note 56 LOF".to_owned(),
                 ],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("April 29, 2022").unwrap(),
                 day_hour: "Friday 5 23 AM".to_owned(),
                 mood: "GOOD".to_owned(),
@@ -904,13 +1277,13 @@ pub(crate) mod tests {
                     "Note 57 MHD".to_owned(),
                 ],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("April 28, 2022").unwrap(),
                 day_hour: "Thursday 5 01 PM".to_owned(),
                 mood: "MOOD 0 KWY".to_owned(),
                 note: vec!["Note title 58 AKY".to_owned(), "Note 58 CHG".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("April 28, 2022").unwrap(),
                 day_hour: "Thursday 8 24 AM".to_owned(),
                 mood: "MOOD 0 KWY".to_owned(),
@@ -920,13 +1293,13 @@ pub(crate) mod tests {
                     "Note 59 XHR".to_owned(),
                 ],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("April 28, 2022").unwrap(),
                 day_hour: "Thursday 7 11 AM".to_owned(),
                 mood: "MOOD 1 QBL".to_owned(),
                 note: vec!["Note title 60 TEO".to_owned(), "Note 60 YQQ".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("April 28, 2022").unwrap(),
                 day_hour: "Thursday 7 02 AM".to_owned(),
                 mood: "GOOD".to_owned(),
@@ -936,13 +1309,13 @@ pub(crate) mod tests {
                     "Note 61 NJC".to_owned(),
                 ],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("April 27, 2022").unwrap(),
                 day_hour: "Wednesday 1 00 PM".to_owned(),
                 mood: "MOOD 1 QBL".to_owned(),
                 note: vec!["Note title 62 OQP".to_owned(), "Note 62 BTP".to_owned()],
             },
-            DayEntry {
+            ParsedDayEntry {
                 date: string_to_date("April 27, 2022").unwrap(),
                 day_hour: "Wednesday 5 30 AM".to_owned(),
                 mood: "MOOD 1 QBL".to_owned(),
@@ -953,6 +1326,7 @@ pub(crate) mod tests {
         let expected = ParsedPdf {
             stats: expected_tags,
             day_entries: expected_entries,
+            warnings: vec![],
         };
 
         assert_eq!(parsed, expected);
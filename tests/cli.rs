@@ -0,0 +1,289 @@
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::process::{Command, Output};
+
+    use color_eyre::Result;
+
+    use daylio_tools::{load_daylio_backup, store_daylio_backup};
+
+    const ENGLISH_BACKUP: &str = "tests/data/official/english.daylio";
+
+    fn run(args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_daylio_tools"))
+            .args(args)
+            .output()
+            .expect("Failed to run daylio_tools")
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("daylio_tools_{name}"))
+    }
+
+    #[test]
+    fn info() -> Result<()> {
+        let result = run(&["info", ENGLISH_BACKUP]);
+        assert!(result.status.success());
+
+        let stdout = String::from_utf8(result.stdout)?;
+        assert!(stdout.contains("Format: Daylio backup"));
+        assert!(stdout.contains("Daylio backup, version 15"));
+        assert!(stdout.contains("5 entries"));
+        assert!(stdout.contains("Valid: yes"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn info_detects_format() -> Result<()> {
+        let mut daylio = load_daylio_backup(ENGLISH_BACKUP.as_ref())?;
+        daylio.day_entries[1].mood = 42;
+        let mislabeled = temp_path("mislabeled.bak");
+        store_daylio_backup(&daylio, &mislabeled)?;
+
+        let result = run(&["info", mislabeled.to_str().unwrap()]);
+        assert!(result.status.success());
+
+        let stdout = String::from_utf8(result.stdout)?;
+        assert!(stdout.contains("Format: Daylio backup"));
+        assert!(stdout.contains("Valid: no, 1 problems"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate() -> Result<()> {
+        let result = run(&["validate", ENGLISH_BACKUP]);
+        assert!(result.status.success());
+        assert!(String::from_utf8(result.stdout)?.contains("No problem found"));
+
+        let mut daylio = load_daylio_backup(ENGLISH_BACKUP.as_ref())?;
+        daylio.day_entries[1].mood = 42;
+        let broken = temp_path("broken.daylio");
+        store_daylio_backup(&daylio, &broken)?;
+
+        let result = run(&["validate", broken.to_str().unwrap()]);
+        assert!(!result.status.success());
+        assert!(String::from_utf8(result.stdout)?.contains("entry 1 uses the unknown mood 42"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate_warns_about_future_entries() -> Result<()> {
+        let mut daylio = load_daylio_backup(ENGLISH_BACKUP.as_ref())?;
+        daylio.day_entries[2].year = 3000;
+        daylio.day_entries[2].datetime = 32503680000000;
+        let future = temp_path("future.daylio");
+        store_daylio_backup(&daylio, &future)?;
+
+        let result = run(&["validate", future.to_str().unwrap()]);
+        assert!(result.status.success());
+
+        let stdout = String::from_utf8(result.stdout)?;
+        assert!(stdout.contains("Warning: entry 2 is dated in the future"));
+        assert!(stdout.contains("No problem found"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn tag_matching_entries() -> Result<()> {
+        let output = temp_path("tagged.daylio");
+
+        let result = run(&[
+            "tag",
+            ENGLISH_BACKUP,
+            output.to_str().unwrap(),
+            "--tag",
+            "line breaks",
+            "--contains",
+            "LINEBREAKS",
+            "--from",
+            "2023-01-01",
+        ]);
+        assert!(result.status.success());
+
+        let daylio = load_daylio_backup(&output)?;
+        let tag = daylio
+            .tags
+            .iter()
+            .find(|tag| tag.name == "line breaks")
+            .unwrap();
+
+        let tagged = daylio
+            .day_entries
+            .iter()
+            .filter(|entry| entry.tags.contains(&tag.id))
+            .collect::<Vec<_>>();
+        assert_eq!(tagged.len(), 1);
+        assert!(tagged[0].note.contains("Linebreaks!"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn explain_merge() -> Result<()> {
+        let output = temp_path("explained.daylio");
+
+        let result = run(&[
+            "merge",
+            ENGLISH_BACKUP,
+            ENGLISH_BACKUP,
+            output.to_str().unwrap(),
+            "--explain",
+        ]);
+        assert!(result.status.success());
+
+        let stdout = String::from_utf8(result.stdout)?;
+        assert!(stdout.contains("2023-01-24"));
+        assert!(stdout.contains(": identical, collapsed"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn same_day_hours_changes_relaxed_merge() -> Result<()> {
+        // the same backup, with an entry logged 3 hours later
+        let mut daylio = load_daylio_backup(ENGLISH_BACKUP.as_ref())?;
+        daylio.day_entries[0].datetime += 3 * 60 * 60 * 1000;
+        daylio.day_entries[0].hour = (daylio.day_entries[0].hour + 3) % 24;
+        let shifted = temp_path("shifted.daylio");
+        store_daylio_backup(&daylio, &shifted)?;
+
+        let merge_entries = |flags: &[&str]| -> Result<usize> {
+            let output = temp_path("same_day_hours.daylio");
+            let mut args = vec![
+                "merge",
+                ENGLISH_BACKUP,
+                shifted.to_str().unwrap(),
+                output.to_str().unwrap(),
+            ];
+            args.extend_from_slice(flags);
+            assert!(run(&args).status.success());
+            Ok(load_daylio_backup(&output)?.day_entries.len())
+        };
+
+        assert_eq!(merge_entries(&[])?, 6);
+        assert_eq!(merge_entries(&["--relaxed"])?, 5);
+        assert_eq!(merge_entries(&["--relaxed", "--same-day-hours", "2"])?, 6);
+        assert_eq!(
+            merge_entries(&["--fuzzy", "1", "--same-day-hours", "4"])?,
+            5
+        );
+
+        // strict merges do not look at the threshold
+        let result = run(&[
+            "merge",
+            ENGLISH_BACKUP,
+            shifted.to_str().unwrap(),
+            temp_path("same_day_hours_strict.daylio").to_str().unwrap(),
+            "--same-day-hours",
+            "2",
+        ]);
+        assert!(!result.status.success());
+
+        Ok(())
+    }
+
+    #[test]
+    fn subset_without_entries() -> Result<()> {
+        let output = temp_path("subset.daylio");
+
+        let result = run(&[
+            "subset",
+            ENGLISH_BACKUP,
+            output.to_str().unwrap(),
+            "--no-entries",
+        ]);
+        assert!(result.status.success());
+
+        let original = load_daylio_backup(ENGLISH_BACKUP.as_ref())?;
+        let subset = load_daylio_backup(&output)?;
+        assert!(subset.day_entries.is_empty());
+        assert_eq!(subset.tags, original.tags);
+        assert_eq!(subset.writing_templates, original.writing_templates);
+
+        Ok(())
+    }
+
+    #[test]
+    fn add_template() -> Result<()> {
+        let output = temp_path("templates.daylio");
+
+        let result = run(&[
+            "templates",
+            ENGLISH_BACKUP,
+            "--add",
+            "My template|Some body",
+            output.to_str().unwrap(),
+        ]);
+        assert!(result.status.success());
+
+        let stdout = String::from_utf8(result.stdout)?;
+        assert!(stdout.contains("9. My template\nSome body"));
+
+        let daylio = load_daylio_backup(&output)?;
+        let added = daylio.writing_templates.last().unwrap();
+        assert_eq!(added.title, "My template");
+        assert_eq!(added.body, "Some body");
+        assert_eq!(added.order, 9);
+        assert_eq!(added.id, 10);
+
+        Ok(())
+    }
+
+    #[test]
+    fn add_reminder() -> Result<()> {
+        let output = temp_path("reminders.daylio");
+
+        let result = run(&[
+            "reminders",
+            ENGLISH_BACKUP,
+            "--set",
+            "07:30",
+            output.to_str().unwrap(),
+        ]);
+        assert!(result.status.success());
+
+        let stdout = String::from_utf8(result.stdout)?;
+        assert!(stdout.contains("20:00"));
+        assert!(stdout.contains("07:30"));
+
+        // pack and extract the result, the reminder must survive
+        let json = temp_path("reminders.json");
+        let repacked = temp_path("reminders_repacked.daylio");
+        assert!(
+            run(&["extract", output.to_str().unwrap(), json.to_str().unwrap()])
+                .status
+                .success()
+        );
+        assert!(
+            run(&["pack", json.to_str().unwrap(), repacked.to_str().unwrap()])
+                .status
+                .success()
+        );
+
+        let daylio = load_daylio_backup(&repacked)?;
+        let added = daylio.reminders.last().unwrap();
+        assert_eq!((added.hour, added.minute), (7, 30));
+        assert_eq!(added.id, 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn add_invalid_reminder() {
+        let output = temp_path("invalid_reminder.daylio");
+
+        let result = run(&[
+            "reminders",
+            ENGLISH_BACKUP,
+            "--set",
+            "25:00",
+            output.to_str().unwrap(),
+        ]);
+
+        assert!(!result.status.success());
+    }
+}
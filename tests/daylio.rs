@@ -0,0 +1,436 @@
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use color_eyre::Result;
+
+    use daylio_tools::{
+        CustomMood, DayEntry, Daylio, load_daylio_backup, load_daylio_json, merge,
+        predefined_mood_name_en, SectionFlags, store_daylio_backup, store_daylio_grouped_json,
+        store_daylio_json, Tag, ValidationError,
+    };
+
+    #[test]
+    fn display_summary() -> Result<()> {
+        let daylio = load_daylio_backup("tests/data/official/english.daylio".as_ref())?;
+
+        let summary = daylio.to_string();
+
+        assert!(summary.contains("version 15"));
+        assert!(summary.contains("5 entries, from 2015-05-16 to 2023-01-24"));
+        assert!(summary.contains("8 moods"));
+        assert!(summary.contains("13 tags"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn predefined_mood_names() {
+        let names = (1..=5)
+            .map(|id| predefined_mood_name_en(id).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["rad", "good", "meh", "bad", "awful"]);
+
+        assert_eq!(predefined_mood_name_en(0), None);
+        assert_eq!(predefined_mood_name_en(6), None);
+        assert_eq!(predefined_mood_name_en(-1), None);
+    }
+
+    #[test]
+    fn display_summary_empty() {
+        let summary = Daylio::default().to_string();
+
+        assert!(summary.contains("0 entries"));
+        assert!(summary.contains("5 moods: rad, good, meh, bad, awful"));
+    }
+
+    #[test]
+    fn future_entries() {
+        let mut daylio = Daylio::default();
+        daylio.day_entries = vec![
+            DayEntry {
+                id: 1,
+                day: 2,
+                month: 7,
+                year: 2022,
+                datetime: 1659463200000,
+                ..Default::default()
+            },
+            DayEntry {
+                id: 2,
+                day: 1,
+                month: 0,
+                year: 3000,
+                datetime: 32503680000000,
+                ..Default::default()
+            },
+        ];
+        daylio.metadata.number_of_entries = 2;
+
+        let now = DateTime::from_timestamp_millis(1700000000000).unwrap();
+
+        assert_eq!(daylio.future_entries(now), vec![1]);
+
+        assert_eq!(daylio.drop_future_entries(now), 1);
+        assert_eq!(daylio.day_entries.len(), 1);
+        assert_eq!(daylio.day_entries[0].year, 2022);
+        assert_eq!(daylio.metadata.number_of_entries, 1);
+    }
+
+    #[test]
+    fn normalize_notes() {
+        let mut daylio = Daylio::default();
+        daylio.day_entries = vec![DayEntry {
+            note: "First line  \r\nSecond line\t\r\n\r\n".to_owned(),
+            note_title: "Title ".to_owned(),
+            ..Default::default()
+        }];
+
+        daylio.normalize_notes();
+
+        assert_eq!(daylio.day_entries[0].note, "First line\nSecond line");
+        assert_eq!(daylio.day_entries[0].note_title, "Title");
+    }
+
+    #[test]
+    fn drop_empty_entries() {
+        let mut daylio = Daylio::default();
+        daylio.day_entries = vec![
+            // a mood-only check-in is real data
+            DayEntry {
+                id: 1,
+                mood: 1,
+                ..Default::default()
+            },
+            DayEntry {
+                id: 2,
+                note: "Only a note".to_owned(),
+                ..Default::default()
+            },
+            // unknown mood, no tags, no assets, blank note and title
+            DayEntry {
+                id: 3,
+                mood: 42,
+                note: " \n".to_owned(),
+                ..Default::default()
+            },
+            DayEntry {
+                id: 4,
+                tags: vec![1],
+                ..Default::default()
+            },
+        ];
+        let moods = daylio.custom_moods.clone();
+
+        assert_eq!(daylio.drop_empty_entries(), 1);
+
+        let ids = daylio
+            .day_entries
+            .iter()
+            .map(|entry| entry.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, [1, 2, 4]);
+        assert_eq!(daylio.metadata.number_of_entries, 3);
+        assert_eq!(daylio.custom_moods, moods);
+    }
+
+    #[test]
+    fn add_tag_after_last_order() {
+        let mut daylio = Daylio::default();
+        // a tag was deleted, so orders have a gap
+        daylio.tags = vec![
+            Tag {
+                id: 1,
+                name: "first".to_owned(),
+                order: 1,
+                ..Default::default()
+            },
+            Tag {
+                id: 3,
+                name: "third".to_owned(),
+                order: 3,
+                ..Default::default()
+            },
+        ];
+        daylio.day_entries = vec![DayEntry {
+            id: 1,
+            mood: 1,
+            ..Default::default()
+        }];
+
+        assert_eq!(daylio.add_tag_where("new", |_| true), 1);
+
+        let added = daylio.tags.last().unwrap();
+        assert_eq!((added.id, added.order), (4, 4));
+        assert_eq!(daylio.day_entries[0].tags, [4]);
+    }
+
+    #[test]
+    fn prune_unused() {
+        let mut daylio = Daylio::default();
+        daylio.custom_moods.extend([
+            CustomMood {
+                id: 6,
+                custom_name: "used".to_owned(),
+                mood_group_id: 1,
+                predefined_name_id: -1,
+                ..Default::default()
+            },
+            CustomMood {
+                id: 7,
+                custom_name: "unused".to_owned(),
+                mood_group_id: 2,
+                predefined_name_id: -1,
+                ..Default::default()
+            },
+        ]);
+        daylio.tags = vec![
+            Tag {
+                id: 1,
+                name: "used".to_owned(),
+                ..Default::default()
+            },
+            Tag {
+                id: 2,
+                name: "unused".to_owned(),
+                ..Default::default()
+            },
+        ];
+        daylio.day_entries = vec![DayEntry {
+            mood: 6,
+            tags: vec![1],
+            ..Default::default()
+        }];
+
+        assert_eq!(daylio.prune_unused(), (1, 1));
+
+        let moods = daylio
+            .custom_moods
+            .iter()
+            .map(|mood| mood.id)
+            .collect::<Vec<_>>();
+        assert_eq!(moods, [1, 2, 3, 4, 5, 6]);
+        assert_eq!(daylio.tags.len(), 1);
+        assert_eq!(daylio.tags[0].name, "used");
+    }
+
+    #[test]
+    fn eq_ignore_order() -> Result<()> {
+        let daylio = load_daylio_backup("tests/data/official/english.daylio".as_ref())?;
+
+        let mut shuffled = daylio.clone();
+        shuffled.day_entries.reverse();
+        shuffled.custom_moods.reverse();
+        shuffled.tags.rotate_left(1);
+
+        assert_ne!(shuffled, daylio);
+        assert!(shuffled.eq_ignore_order(&daylio));
+
+        shuffled.day_entries[0].note.push('!');
+        assert!(!shuffled.eq_ignore_order(&daylio));
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate_finds_problems() {
+        assert_eq!(Daylio::default().validate(), []);
+
+        let mut daylio = Daylio::default();
+        daylio
+            .custom_moods
+            .retain(|mood| mood.predefined_name_id != 3);
+        let tag = Tag {
+            id: 1,
+            name: "tag".to_owned(),
+            id_tag_group: 1,
+            ..Default::default()
+        };
+        daylio.tags = vec![tag.clone(), tag];
+        daylio.day_entries = vec![
+            DayEntry {
+                id: 1,
+                mood: 1,
+                tags: vec![1, 2],
+                ..Default::default()
+            },
+            DayEntry {
+                id: 1,
+                mood: 3,
+                ..Default::default()
+            },
+        ];
+
+        assert_eq!(
+            daylio.validate(),
+            [
+                ValidationError::MissingPredefinedMood(3),
+                ValidationError::DuplicateTagId(1),
+                ValidationError::UnknownTag { entry: 0, tag: 2 },
+                ValidationError::DuplicateEntryId { entry: 1, id: 1 },
+                ValidationError::UnknownMood { entry: 1, mood: 3 },
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_without_entries() {
+        let mut daylio = Daylio::default();
+        daylio
+            .custom_moods
+            .retain(|mood| mood.predefined_name_id != 5);
+
+        assert!(daylio.day_entries.is_empty());
+        assert_eq!(
+            daylio.validate(),
+            [ValidationError::MissingPredefinedMood(5)]
+        );
+    }
+
+    #[test]
+    fn validate_tags_without_groups() {
+        let mut daylio = Daylio::default();
+        daylio.tag_groups.clear();
+        daylio.tags = vec![
+            Tag {
+                id: 1,
+                name: "old".to_owned(),
+                id_tag_group: 0,
+                ..Default::default()
+            },
+            Tag {
+                id: 2,
+                name: "dangling".to_owned(),
+                id_tag_group: 3,
+                ..Default::default()
+            },
+        ];
+
+        assert_eq!(
+            daylio.validate(),
+            [ValidationError::UnknownTagGroup { tag: 2, group: 3 }]
+        );
+    }
+
+    #[test]
+    fn subset_without_entries() -> Result<()> {
+        let original = load_daylio_backup("tests/data/official/english.daylio".as_ref())?;
+
+        let template = original.subset(SectionFlags {
+            entries: false,
+            ..SectionFlags::ALL
+        });
+
+        let path = std::env::temp_dir().join("daylio_tools_template.daylio");
+        store_daylio_backup(&template, &path)?;
+        let template = load_daylio_backup(&path)?;
+
+        assert!(template.day_entries.is_empty());
+        assert_eq!(template.metadata.number_of_entries, 0);
+        assert_eq!(template.custom_moods, original.custom_moods);
+        assert_eq!(template.tags, original.tags);
+        for id in 1..=5 {
+            assert!(template
+                .custom_moods
+                .iter()
+                .any(|mood| mood.predefined_name_id == id));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn subset_without_custom_moods() -> Result<()> {
+        let original = load_daylio_backup("tests/data/official/english.daylio".as_ref())?;
+
+        let subset = original.subset(SectionFlags {
+            moods: false,
+            ..SectionFlags::ALL
+        });
+
+        assert_eq!(subset.custom_moods.len(), 5);
+        assert_eq!(subset.day_entries.len(), original.day_entries.len());
+        // the custom mood 8 belongs to the group of the predefined mood 4
+        let moods = subset
+            .day_entries
+            .iter()
+            .map(|entry| entry.mood)
+            .collect::<Vec<_>>();
+        assert_eq!(moods, [5, 1, 3, 5, 4]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn mood_icons_survive_round_trip() -> Result<()> {
+        let original = load_daylio_backup("tests/data/official/english.daylio".as_ref())?;
+        let icons = original
+            .preferred_mood_icons_ids_for_mood_ids_for_icons_pack
+            .clone();
+        assert_ne!(
+            icons,
+            Daylio::default().preferred_mood_icons_ids_for_mood_ids_for_icons_pack
+        );
+
+        let json = std::env::temp_dir().join("daylio_tools_icons.json");
+        let backup = std::env::temp_dir().join("daylio_tools_icons.daylio");
+        store_daylio_json(&original, &json)?;
+        store_daylio_backup(&load_daylio_json(&json)?, &backup)?;
+        let round_tripped = load_daylio_backup(&backup)?;
+
+        assert_eq!(
+            round_tripped.preferred_mood_icons_ids_for_mood_ids_for_icons_pack,
+            icons
+        );
+
+        // the reference file's preferences are kept when merging
+        let merged = merge(round_tripped, Daylio::default());
+        assert_eq!(
+            merged.preferred_mood_icons_ids_for_mood_ids_for_icons_pack,
+            icons
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn grouped_json() -> Result<()> {
+        let entry = |id, day, hour, datetime| DayEntry {
+            id,
+            day,
+            hour,
+            month: 0,
+            year: 2024,
+            datetime,
+            ..Default::default()
+        };
+
+        let mut daylio = Daylio::default();
+        daylio.day_entries = vec![
+            entry(1, 1, 20, 1704139200000),
+            entry(2, 2, 9, 1704186000000),
+            entry(3, 1, 8, 1704096000000),
+        ];
+
+        let path = std::env::temp_dir().join("daylio_tools_grouped.json");
+        store_daylio_grouped_json(&daylio, &path)?;
+
+        let grouped: serde_json::Value = serde_json::from_reader(std::fs::File::open(path)?)?;
+        let days = grouped.as_object().unwrap();
+
+        assert_eq!(
+            days.keys().collect::<Vec<_>>(),
+            ["2024-01-01", "2024-01-02"]
+        );
+
+        let ids = days["2024-01-01"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["id"].as_i64().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, [3, 1]);
+        assert_eq!(days["2024-01-02"].as_array().unwrap().len(), 1);
+
+        Ok(())
+    }
+}
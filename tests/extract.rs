@@ -1,10 +1,15 @@
 #[cfg(test)]
 mod tests {
+    use chrono::NaiveDate;
     use color_eyre::Result;
     use similar_asserts::assert_eq;
 
     use daylio_tools::{
-        CustomMood, DayEntry, Daylio, load_daylio_backup, load_daylio_pdf, Metadata, Tag,
+        CsvNewlines, CsvOptions, CustomMood, DayEntry, Daylio, load_daylio, load_daylio_any,
+        load_daylio_backup, load_daylio_backup_with_limit, load_daylio_csv,
+        load_daylio_csv_with_options, load_daylio_pdf, load_daylio_pdf_with_dumps, Metadata,
+        parse_pdf, PdfDumps, store_daylio_backup, store_daylio_csv, store_daylio_csv_with_options,
+        Tag,
     };
 
     #[test]
@@ -19,6 +24,381 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn url_safe_backup() -> Result<()> {
+        use std::io::{Read, Write};
+
+        use base64::engine::general_purpose::{STANDARD, URL_SAFE};
+        use base64::Engine;
+
+        let path = "tests/data/official/english.daylio";
+
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(path)?)?;
+        let mut encoded = String::new();
+        archive
+            .by_name("backup.daylio")?
+            .read_to_string(&mut encoded)?;
+        let json = STANDARD.decode(encoded.replace('\n', ""))?;
+
+        let url_safe = URL_SAFE.encode(json);
+        assert!(url_safe.contains(['-', '_']));
+
+        let output = std::env::temp_dir().join("daylio_tools_url_safe.daylio");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&output)?);
+        zip.start_file("backup.daylio", zip::write::SimpleFileOptions::default())?;
+        zip.write_all(url_safe.as_bytes())?;
+        zip.finish()?;
+
+        let expected = load_daylio_backup(path.as_ref())?;
+        assert_eq!(load_daylio_backup(&output)?, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn mislabeled_backup() -> Result<()> {
+        let path = std::env::temp_dir().join("daylio_tools_mislabeled.txt");
+        std::fs::copy("tests/data/official/english.daylio", &path)?;
+
+        let expected = load_daylio_backup("tests/data/official/english.daylio".as_ref())?;
+
+        assert_eq!(load_daylio_any(&path)?, expected);
+        assert_eq!(load_daylio(&path)?, expected);
+
+        let error = load_daylio_any("tests/data/new_extracted.txt".as_ref()).unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains("Daylio backup: "));
+        assert!(message.contains("JSON: "));
+        assert!(message.contains("PDF: "));
+
+        Ok(())
+    }
+
+    #[test]
+    fn csv_format() -> Result<()> {
+        use std::collections::BTreeSet;
+
+        let actual = load_daylio_csv("tests/data/export.csv".as_ref())?;
+
+        assert_eq!(actual.day_entries.len(), 3);
+        assert_eq!(actual.metadata.number_of_entries, 3);
+
+        let entry = |day| {
+            actual
+                .day_entries
+                .iter()
+                .find(|entry| entry.day == day)
+                .unwrap()
+        };
+        let tag_names = |entry: &DayEntry| {
+            entry
+                .tags
+                .iter()
+                .map(|id| {
+                    let tag = actual.tags.iter().find(|tag| tag.id == *id).unwrap();
+                    tag.name.as_str()
+                })
+                .collect::<BTreeSet<_>>()
+        };
+
+        let commas = entry(24);
+        assert_eq!(
+            (commas.year, commas.month, commas.hour, commas.minute),
+            (2023, 0, 20, 5)
+        );
+        assert_eq!(commas.mood, 1);
+        assert_eq!(commas.note_title, "Note title");
+        assert_eq!(commas.note, "Note body, with a comma");
+        assert_eq!(tag_names(commas), BTreeSet::from(["sport", "work"]));
+
+        let linebreaks = entry(11);
+        assert_eq!((linebreaks.hour, linebreaks.minute), (8, 30));
+        assert_eq!(linebreaks.note, "Linebreaks!\nSecond line with \"quotes\"");
+        assert!(linebreaks.tags.is_empty());
+        let mood = actual
+            .custom_moods
+            .iter()
+            .find(|mood| mood.id == linebreaks.mood)
+            .unwrap();
+        assert_eq!(mood.custom_name, "tired");
+        assert_eq!(mood.predefined_name_id, -1);
+
+        let old = entry(16);
+        assert_eq!((old.year, old.month, old.hour), (2015, 4, 12));
+        assert_eq!(old.mood, 3);
+        assert_eq!(tag_names(old), BTreeSet::from(["sport"]));
+
+        // the extension is enough to pick the CSV loader
+        assert_eq!(load_daylio("tests/data/export.csv".as_ref())?, actual);
+
+        Ok(())
+    }
+
+    #[test]
+    fn csv_round_trip() -> Result<()> {
+        let original = load_daylio_backup("tests/data/official/english.daylio".as_ref())?;
+
+        let output = std::env::temp_dir().join("daylio_tools_export.csv");
+        store_daylio_csv(&original, &output)?;
+        let actual = load_daylio_csv(&output)?;
+
+        // the CSV has no time zone, so compare the local date and time
+        let summary = |daylio: &Daylio| {
+            let mut entries = daylio
+                .day_entries
+                .iter()
+                .map(|entry| {
+                    let time = (entry.year, entry.month, entry.day, entry.hour, entry.minute);
+                    (time, entry.note_title.clone(), entry.note.clone())
+                })
+                .collect::<Vec<_>>();
+            entries.sort();
+            entries
+        };
+
+        assert_eq!(actual.day_entries.len(), original.day_entries.len());
+        assert_eq!(summary(&actual), summary(&original));
+
+        Ok(())
+    }
+
+    #[test]
+    fn csv_keeps_note_title_apart() -> Result<()> {
+        let mut daylio = Daylio::default();
+        daylio.day_entries = vec![DayEntry {
+            id: 1,
+            hour: 20,
+            day: 2,
+            month: 7,
+            year: 2022,
+            datetime: 1659470400000,
+            mood: 2,
+            note_title: "A title, with a comma".to_owned(),
+            note: "A body\non two lines".to_owned(),
+            ..Default::default()
+        }];
+
+        let path = std::env::temp_dir().join("daylio_tools_title.csv");
+        store_daylio_csv(&daylio, &path)?;
+        let actual = load_daylio_csv(&path)?;
+
+        let entry = &actual.day_entries[0];
+        assert_eq!(entry.note_title, "A title, with a comma");
+        assert_eq!(entry.note, "A body\non two lines");
+
+        Ok(())
+    }
+
+    #[test]
+    fn csv_semicolon_round_trip() -> Result<()> {
+        let mut daylio = Daylio::default();
+        daylio.day_entries = vec![DayEntry {
+            id: 1,
+            hour: 20,
+            day: 2,
+            month: 7,
+            year: 2022,
+            datetime: 1659470400000,
+            mood: 2,
+            note: "Tea; then a walk, in the rain".to_owned(),
+            ..Default::default()
+        }];
+
+        let options = CsvOptions {
+            delimiter: b';',
+            ..Default::default()
+        };
+        let path = std::env::temp_dir().join("daylio_tools_semicolon.csv");
+        store_daylio_csv_with_options(&daylio, &path, options)?;
+
+        let csv = std::fs::read_to_string(&path)?;
+        assert!(csv.starts_with("full_date;date;weekday;time;mood;"));
+        assert!(csv.contains("\"Tea; then a walk, in the rain\""));
+
+        let actual = load_daylio_csv_with_options(&path, options)?;
+        assert_eq!(actual.day_entries[0].note, "Tea; then a walk, in the rain");
+
+        Ok(())
+    }
+
+    #[test]
+    fn csv_multi_line_notes() -> Result<()> {
+        let mut daylio = Daylio::default();
+        daylio.day_entries = vec![DayEntry {
+            id: 1,
+            hour: 20,
+            day: 2,
+            month: 7,
+            year: 2022,
+            datetime: 1659470400000,
+            mood: 2,
+            note: "First line\nSecond line".to_owned(),
+            ..Default::default()
+        }];
+
+        let write = |newlines| -> Result<String> {
+            let path = std::env::temp_dir().join("daylio_tools_newlines.csv");
+            let options = CsvOptions {
+                newlines,
+                ..Default::default()
+            };
+            store_daylio_csv_with_options(&daylio, &path, options)?;
+            Ok(std::fs::read_to_string(path)?)
+        };
+
+        assert!(write(CsvNewlines::Quote)?.contains(",\"First line\nSecond line\"\n"));
+        assert!(write(CsvNewlines::Escape)?.contains(",First line\\nSecond line\n"));
+        assert!(write(CsvNewlines::Space)?.contains(",First line Second line\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn csv_keeps_commas_in_tags() -> Result<()> {
+        let mut daylio = Daylio::default();
+        daylio.tags = vec![Tag {
+            id: 1,
+            name: "friends, family".to_owned(),
+            ..Default::default()
+        }];
+        daylio.day_entries = vec![DayEntry {
+            id: 1,
+            hour: 20,
+            day: 2,
+            month: 7,
+            year: 2022,
+            datetime: 1659470400000,
+            mood: 2,
+            tags: vec![1],
+            ..Default::default()
+        }];
+
+        let path = std::env::temp_dir().join("daylio_tools_tags.csv");
+        store_daylio_csv(&daylio, &path)?;
+        let actual = load_daylio_csv(&path)?;
+
+        let names = actual
+            .tags
+            .iter()
+            .map(|tag| tag.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["friends, family"]);
+        assert_eq!(actual.day_entries[0].tags.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn stored_backup_is_compressed() -> Result<()> {
+        use base64::engine::general_purpose::STANDARD;
+        use base64::Engine;
+
+        let daylio = load_daylio_backup("tests/data/new.daylio".as_ref())?;
+
+        let path = std::env::temp_dir().join("daylio_tools_compressed.daylio");
+        store_daylio_backup(&daylio, &path)?;
+
+        // without compression, the archive would be at least as large as its content
+        let content = STANDARD.encode(serde_json::to_string_pretty(&daylio)?);
+        assert!(std::fs::metadata(&path)?.len() < content.len() as u64);
+
+        assert_eq!(load_daylio_backup(&path)?, daylio);
+
+        Ok(())
+    }
+
+    #[test]
+    fn backup_size_limit() {
+        let path = "tests/data/old.daylio".as_ref();
+
+        let error = load_daylio_backup_with_limit(path, 1024).unwrap_err();
+        assert!(error.to_string().contains("limit of 1024 bytes"));
+
+        assert!(load_daylio_backup_with_limit(path, 1024 * 1024).is_ok());
+        assert!(load_daylio_backup_with_limit(path, u64::MAX).is_ok());
+    }
+
+    #[test]
+    fn dump_pdf_text() -> Result<()> {
+        let output = std::env::temp_dir().join("daylio_tools_dump.txt");
+        let dumps = PdfDumps {
+            text: Some(output.clone()),
+            ..PdfDumps::default()
+        };
+        load_daylio_pdf_with_dumps("tests/data/new.pdf".as_ref(), &dumps, false)?;
+
+        let actual = std::fs::read_to_string(output)?;
+        let expected = std::fs::read_to_string("tests/data/new_extracted.txt")?;
+
+        assert_eq!(actual, expected.replace("\r\n", "\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn dump_parsed_pdf() -> Result<()> {
+        let output = std::env::temp_dir().join("daylio_tools_parsed.json");
+        let dumps = PdfDumps {
+            parsed: Some(output.clone()),
+            ..PdfDumps::default()
+        };
+        load_daylio_pdf_with_dumps("tests/data/new.pdf".as_ref(), &dumps, false)?;
+
+        let parsed: serde_json::Value = serde_json::from_reader(std::fs::File::open(output)?)?;
+
+        let stats = parsed["stats"].as_array().unwrap();
+        assert_eq!(stats.len(), 25);
+        assert_eq!(stats[0], serde_json::json!({ "name": "rad", "count": 15 }));
+        assert_eq!(
+            stats[24],
+            serde_json::json!({ "name": "Tag 4 HBK", "count": 10 })
+        );
+
+        let first_entry = &parsed["day_entries"][0];
+        assert_eq!(first_entry["date"].as_str(), Some("2022-08-02"));
+        assert_eq!(first_entry["mood"].as_str(), Some("MOOD 2 VUP"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn load_pdf_with_dumps() -> Result<()> {
+        let path = "tests/data/new.pdf".as_ref();
+        let dumps = PdfDumps {
+            text: Some(std::env::temp_dir().join("daylio_tools_dumps.txt")),
+            parsed: Some(std::env::temp_dir().join("daylio_tools_dumps.json")),
+        };
+
+        let (daylio, warnings) = load_daylio_pdf_with_dumps(path, &dumps, false)?;
+        assert_eq!(daylio, load_daylio_pdf(path)?);
+        assert!(warnings.is_empty());
+
+        let text = std::fs::read_to_string(dumps.text.unwrap())?;
+        let expected = std::fs::read_to_string("tests/data/new_extracted.txt")?;
+        assert_eq!(text, expected.replace("\r\n", "\n"));
+
+        let parsed: serde_json::Value =
+            serde_json::from_reader(std::fs::File::open(dumps.parsed.unwrap())?)?;
+        assert_eq!(parsed["stats"].as_array().unwrap().len(), 25);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_pdf_without_interpreting() -> Result<()> {
+        let parsed = parse_pdf("tests/data/new.pdf".as_ref())?;
+
+        assert_eq!(parsed.stats.len(), 25);
+        assert_eq!(parsed.stats[0].name, "rad");
+
+        let first = &parsed.day_entries[0];
+        assert_eq!(first.date, NaiveDate::from_ymd_opt(2022, 8, 2).unwrap());
+        assert_eq!(first.mood, "MOOD 2 VUP");
+        assert!(parsed.warnings.is_empty());
+
+        Ok(())
+    }
+
     #[test]
     /// This test shows information lost when converting from PDF to JSON.
     /// This is not so bad! The PDF format is not meant to be machine-readable.
@@ -31,6 +411,25 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn pdf_tags_have_existing_group() -> Result<()> {
+        let daylio = load_daylio_pdf("tests/data/official/english.pdf".as_ref())?;
+
+        for tag in &daylio.tags {
+            let group_exists = daylio
+                .tag_groups
+                .iter()
+                .any(|group| group.id == tag.id_tag_group);
+            assert!(
+                group_exists,
+                "Tag {} references missing group {}",
+                tag.name, tag.id_tag_group
+            );
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn pdf_format_french() -> Result<()> {
         let actual = load_daylio_pdf("tests/data/official/french.pdf".as_ref())?;
@@ -70,7 +469,7 @@ mod tests {
                     icon: 0,
                     order: 1,
                     state: 0,
-                    id_tag_group: 0,
+                    id_tag_group: 1,
                 },
                 Tag {
                     id: 2,
@@ -79,7 +478,7 @@ mod tests {
                     icon: 0,
                     order: 2,
                     state: 0,
-                    id_tag_group: 0,
+                    id_tag_group: 1,
                 },
                 Tag {
                     id: 3,
@@ -88,7 +487,7 @@ mod tests {
                     icon: 0,
                     order: 3,
                     state: 0,
-                    id_tag_group: 0,
+                    id_tag_group: 1,
                 },
                 Tag {
                     id: 4,
@@ -97,7 +496,7 @@ mod tests {
                     icon: 0,
                     order: 4,
                     state: 0,
-                    id_tag_group: 0,
+                    id_tag_group: 1,
                 },
                 Tag {
                     id: 5,
@@ -106,7 +505,7 @@ mod tests {
                     icon: 0,
                     order: 5,
                     state: 0,
-                    id_tag_group: 0,
+                    id_tag_group: 1,
                 },
                 Tag {
                     id: 6,
@@ -115,7 +514,7 @@ mod tests {
                     icon: 0,
                     order: 6,
                     state: 0,
-                    id_tag_group: 0,
+                    id_tag_group: 1,
                 },
                 Tag {
                     id: 7,
@@ -124,7 +523,7 @@ mod tests {
                     icon: 0,
                     order: 7,
                     state: 0,
-                    id_tag_group: 0,
+                    id_tag_group: 1,
                 },
                 Tag {
                     id: 8,
@@ -133,7 +532,7 @@ mod tests {
                     icon: 0,
                     order: 8,
                     state: 0,
-                    id_tag_group: 0,
+                    id_tag_group: 1,
                 },
             ],
             day_entries: vec![
@@ -1,8 +1,14 @@
 #[cfg(test)]
 mod tests {
+    use chrono::{Datelike, NaiveDate, TimeDelta, Timelike};
     use color_eyre::Result;
+    use nanorand::{Rng, WyRand};
 
-    use daylio_tools::{CustomMood, DayEntry, Daylio, load_daylio_backup, merge, Tag};
+    use daylio_tools::{
+        CustomMood, DayEntry, DayEntryComparisonPolicy, Daylio, largest_daylio,
+        load_daylio_backup, merge, merge_with_options, merge_with_policy, MergeOptions,
+        SimplifyOptions, store_daylio_backup, Tag,
+    };
 
     fn base_input() -> Daylio {
         Daylio {
@@ -176,7 +182,7 @@ mod tests {
                 icon: 91,
                 order: 2,
                 state: 0,
-                id_tag_group: 2,
+                id_tag_group: 1, // group 2 does not exist, so the tag is moved to the default one
             },
         ];
 
@@ -199,6 +205,378 @@ mod tests {
         Ok(())
     }
 
+    fn entry_with_note(note: &str) -> DayEntry {
+        DayEntry {
+            id: 1,
+            minute: 0,
+            hour: 20,
+            day: 2,
+            month: 7,
+            year: 2022,
+            datetime: 1659463200000,
+            time_zone_offset: 7200000,
+            mood: 1,
+            note: note.to_owned(),
+            note_title: "".to_owned(),
+            tags: vec![],
+            assets: vec![],
+        }
+    }
+
+    #[test]
+    fn sanitize_repairs_tag_groups() {
+        let mut daylio = input1();
+        daylio.tags[0].id_tag_group = 999;
+
+        daylio.sanitize();
+
+        assert!(daylio.tags.iter().all(|tag| tag.id_tag_group == 1));
+
+        daylio.tag_groups.clear();
+        daylio.tags[0].id_tag_group = 0;
+        daylio.sanitize();
+
+        assert_eq!(daylio.tag_groups, Daylio::default().tag_groups);
+        assert!(daylio.tags.iter().all(|tag| tag.id_tag_group == 1));
+    }
+
+    #[test]
+    fn merge_into_backup_without_tag_groups() -> Result<()> {
+        let reference = load_daylio_backup("tests/data/old.daylio".as_ref())?;
+        assert!(reference.tag_groups.is_empty());
+
+        let merged = merge(reference, input1());
+
+        assert_eq!(merged.tag_groups, Daylio::default().tag_groups);
+        assert!(merged.tags.iter().all(|tag| tag.id_tag_group == 1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn largest_daylio_is_reference() {
+        let with_entries = |count: usize| {
+            let mut daylio = base_input();
+            daylio.day_entries = vec![entry_with_note("note"); count];
+            daylio
+        };
+
+        let daylios = [with_entries(1), with_entries(3), with_entries(2)];
+        assert_eq!(largest_daylio(&daylios), Some(1));
+
+        // the first one wins on ties
+        let daylios = [with_entries(2), with_entries(1), with_entries(2)];
+        assert_eq!(largest_daylio(&daylios), Some(0));
+
+        assert_eq!(largest_daylio(&[]), None);
+    }
+
+    #[test]
+    fn same_time_entries_merge_deterministically() {
+        let mut input1 = base_input();
+        input1.day_entries = vec![entry_with_note("b"), entry_with_note("a")];
+
+        let mut input2 = base_input();
+        input2.day_entries = vec![entry_with_note("b")];
+
+        let merged = merge(input1.clone(), input2.clone());
+        assert_eq!(merged, merge(input2, input1));
+
+        // the duplicate is found even though another entry has the same time
+        let notes = merged
+            .day_entries
+            .iter()
+            .map(|entry| entry.note.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(notes, ["b", "a"]);
+    }
+
+    #[test]
+    fn strict_policy_keeps_near_identical_notes() {
+        let mut input1 = base_input();
+        input1.day_entries = vec![entry_with_note("Went for a walk.")];
+
+        let mut input2 = base_input();
+        input2.day_entries = vec![entry_with_note("went for a walk")];
+
+        let merged = merge_with_policy(input1.clone(), input2, DayEntryComparisonPolicy::Strict);
+        assert_eq!(merged.day_entries.len(), 2);
+
+        let merged = merge_with_policy(input1.clone(), input1, DayEntryComparisonPolicy::Strict);
+        assert_eq!(merged.day_entries.len(), 1);
+    }
+
+    #[test]
+    fn relaxed_policy_collapses_near_identical_notes() {
+        let mut input1 = base_input();
+        input1.day_entries = vec![entry_with_note("went for a walk")];
+
+        let mut input2 = base_input();
+        let mut later = entry_with_note("Went for a walk.");
+        later.datetime += 60 * 60 * 1000;
+        later.hour += 1;
+        input2.day_entries = vec![later];
+
+        let policy = DayEntryComparisonPolicy::Relaxed(SimplifyOptions::default());
+        let merged = merge_with_policy(input1, input2, policy);
+
+        assert_eq!(merged.day_entries.len(), 1);
+        assert_eq!(merged.day_entries[0].note, "Went for a walk.");
+    }
+
+    #[test]
+    fn relaxed_policy_keeps_similar_entries_of_one_file() {
+        let mut input1 = base_input();
+        let mut later = entry_with_note("");
+        later.datetime += 60 * 60 * 1000;
+        later.hour += 1;
+        input1.day_entries = vec![entry_with_note(""), later];
+
+        let policy = DayEntryComparisonPolicy::Relaxed(SimplifyOptions::default());
+        let merged = merge_with_policy(input1.clone(), base_input(), policy);
+        assert_eq!(merged.day_entries.len(), 2);
+
+        // each entry of the other file only stands for one of them
+        let mut input2 = base_input();
+        input2.day_entries = vec![entry_with_note("")];
+        let merged = merge_with_policy(input1, input2, policy);
+        assert_eq!(merged.day_entries.len(), 2);
+    }
+
+    #[test]
+    fn same_day_threshold_limits_relaxed_matching() {
+        let mut input1 = base_input();
+        input1.day_entries = vec![entry_with_note("went for a walk")];
+
+        let mut input2 = base_input();
+        let mut later = entry_with_note("Went for a walk.");
+        later.datetime += 3 * 60 * 60 * 1000;
+        later.hour += 3;
+        input2.day_entries = vec![later];
+
+        let policy = DayEntryComparisonPolicy::Relaxed(SimplifyOptions::default());
+        let merged = merge_with_options(
+            input1.clone(),
+            input2.clone(),
+            policy,
+            MergeOptions::default(),
+        );
+        assert_eq!(merged.day_entries.len(), 1);
+
+        let options = MergeOptions {
+            same_day_threshold: TimeDelta::hours(2),
+        };
+        let merged = merge_with_options(input1, input2, policy, options);
+        assert_eq!(merged.day_entries.len(), 2);
+    }
+
+    #[test]
+    fn fuzzy_policy_collapses_typo_fixes() {
+        let mut input1 = base_input();
+        input1.day_entries = vec![entry_with_note("Went for a wlak")];
+
+        let mut input2 = base_input();
+        input2.day_entries = vec![entry_with_note("Went for a walk!")];
+
+        let policy = DayEntryComparisonPolicy::Relaxed(SimplifyOptions::default());
+        let merged = merge_with_policy(input1.clone(), input2.clone(), policy);
+        assert_eq!(merged.day_entries.len(), 2);
+
+        // swapping two letters takes two edits
+        let policy = DayEntryComparisonPolicy::Fuzzy { max_distance: 1 };
+        let merged = merge_with_policy(input1.clone(), input2.clone(), policy);
+        assert_eq!(merged.day_entries.len(), 2);
+
+        let policy = DayEntryComparisonPolicy::Fuzzy { max_distance: 2 };
+        let merged = merge_with_policy(input1, input2, policy);
+        assert_eq!(merged.day_entries.len(), 1);
+        assert_eq!(merged.day_entries[0].note, "Went for a walk!");
+    }
+
+    #[test]
+    fn fuzzy_policy_keeps_long_different_notes() {
+        let mut input1 = base_input();
+        input1.day_entries = vec![entry_with_note(&"a".repeat(10_000))];
+
+        let mut input2 = base_input();
+        input2.day_entries = vec![entry_with_note(&"b".repeat(10_000))];
+
+        let policy = DayEntryComparisonPolicy::Fuzzy { max_distance: 3 };
+        let merged = merge_with_policy(input1, input2, policy);
+        assert_eq!(merged.day_entries.len(), 2);
+    }
+
+    #[test]
+    fn merge_keeps_assets() {
+        let photo = |id: i64| serde_json::json!({ "id": id, "checksum": format!("photo {id}") });
+
+        let mut input1 = base_input();
+        let mut entry1 = entry_with_note("went for a walk");
+        entry1.assets = vec![1.into()];
+        input1.day_entries = vec![entry1];
+        input1.assets = vec![photo(1)];
+        input1.metadata.number_of_photos = 1;
+        input1.metadata.photos_size = 100;
+
+        let mut input2 = base_input();
+        let mut entry2 = entry_with_note("Went for a walk.");
+        entry2.assets = vec![2.into()];
+        input2.day_entries = vec![entry2];
+        input2.assets = vec![photo(1), photo(2)];
+        input2.metadata.number_of_photos = 2;
+        input2.metadata.photos_size = 300;
+
+        let merged = merge(input1.clone(), input2.clone());
+        assert_eq!(merged.assets, [photo(1), photo(2)]);
+        assert_eq!(merged.day_entries.len(), 2);
+        assert_eq!(merged.metadata.number_of_photos, 2);
+        assert_eq!(merged.metadata.photos_size, 250);
+
+        // shared photos are only counted once
+        let merged = merge(input2.clone(), input2.clone());
+        assert_eq!(merged.metadata.number_of_photos, 2);
+        assert_eq!(merged.metadata.photos_size, 300);
+
+        // the collapsed entry gives its photos to the kept one
+        let policy = DayEntryComparisonPolicy::Relaxed(SimplifyOptions::default());
+        let merged = merge_with_policy(input1, input2, policy);
+        assert_eq!(merged.day_entries.len(), 1);
+        let assets = &merged.day_entries[0].assets;
+        assert_eq!(assets.len(), 2);
+        assert!(assets.contains(&1.into()) && assets.contains(&2.into()));
+    }
+
+    #[test]
+    fn relaxed_policy_folds_accents_only_when_asked() {
+        let mut input1 = base_input();
+        input1.day_entries = vec![entry_with_note("Élan")];
+
+        let mut input2 = base_input();
+        input2.day_entries = vec![entry_with_note("elan")];
+
+        let policy = DayEntryComparisonPolicy::Relaxed(SimplifyOptions::default());
+        let merged = merge_with_policy(input1.clone(), input2.clone(), policy);
+        assert_eq!(merged.day_entries.len(), 2);
+
+        let policy = DayEntryComparisonPolicy::Relaxed(SimplifyOptions {
+            fold_accents: true,
+            ..Default::default()
+        });
+        let merged = merge_with_policy(input1, input2, policy);
+        assert_eq!(merged.day_entries.len(), 1);
+        assert_eq!(merged.day_entries[0].note, "Élan");
+    }
+
+    #[test]
+    fn relaxed_policy_ignores_digits_only_when_asked() {
+        let mut input1 = base_input();
+        input1.day_entries = vec![entry_with_note("Ran 5 km")];
+
+        let mut input2 = base_input();
+        input2.day_entries = vec![entry_with_note("ran km")];
+
+        let policy = DayEntryComparisonPolicy::Relaxed(SimplifyOptions::default());
+        let merged = merge_with_policy(input1.clone(), input2.clone(), policy);
+        assert_eq!(merged.day_entries.len(), 2);
+
+        let policy = DayEntryComparisonPolicy::Relaxed(SimplifyOptions {
+            ignore_digits: true,
+            ..Default::default()
+        });
+        let merged = merge_with_policy(input1, input2, policy);
+        assert_eq!(merged.day_entries.len(), 1);
+    }
+
+    /// Builds a small sanitized backup, without having to commit a binary file.
+    /// The same seed always gives the same backup
+    fn generate_fixture(seed: u64) -> Daylio {
+        let mut rng = WyRand::new_seed(seed);
+        let mut daylio = Daylio::default();
+
+        // ids are far from the final ones, so that sanitize does not mix them up
+        for group in 1..=3 {
+            daylio.custom_moods.push(CustomMood {
+                id: 100 + group,
+                custom_name: format!("Mood {group}"),
+                mood_group_id: group,
+                icon_id: 1,
+                predefined_name_id: -1,
+                ..Default::default()
+            });
+        }
+
+        for i in 0..5 {
+            daylio.tags.push(Tag {
+                id: 100 + i,
+                name: format!("Tag {i}"),
+                created_at: 1651129353707 + i,
+                icon: 1,
+                order: i + 1,
+                state: 0,
+                id_tag_group: 1,
+            });
+        }
+
+        let mood_ids = daylio.custom_moods.iter().map(|m| m.id).collect::<Vec<_>>();
+        let tag_ids = daylio.tags.iter().map(|t| t.id).collect::<Vec<_>>();
+
+        let start = NaiveDate::from_ymd_opt(2022, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        for i in 0..30 {
+            // one entry per day, so that no two entries share a timestamp
+            let date = start
+                + TimeDelta::days(i)
+                + TimeDelta::minutes(i64::from(rng.generate_range(0u16..24 * 60)));
+            let tags = tag_ids
+                .iter()
+                .copied()
+                .filter(|_| rng.generate_range(0u8..2) == 0)
+                .collect();
+
+            daylio.day_entries.push(DayEntry {
+                id: i + 1,
+                minute: i64::from(date.minute()),
+                hour: i64::from(date.hour()),
+                day: i64::from(date.day()),
+                month: i64::from(date.month0()),
+                year: i64::from(date.year()),
+                datetime: date.and_utc().timestamp_millis(),
+                time_zone_offset: 0,
+                mood: mood_ids[rng.generate_range(0..mood_ids.len())],
+                note: format!("Note {i}"),
+                note_title: "".to_owned(),
+                tags,
+                assets: vec![],
+            });
+        }
+
+        daylio.sanitize();
+        daylio.metadata.number_of_entries = daylio.day_entries.len() as i64;
+
+        daylio
+    }
+
+    #[test]
+    fn generated_fixture_is_deterministic() {
+        assert_eq!(generate_fixture(42), generate_fixture(42));
+        assert_ne!(generate_fixture(42), generate_fixture(43));
+    }
+
+    #[test]
+    fn merge_generated_fixture_with_itself() -> Result<()> {
+        let path = std::env::temp_dir().join("daylio_tools_generated_fixture.daylio");
+        store_daylio_backup(&generate_fixture(42), &path)?;
+        let fixture = load_daylio_backup(&path)?;
+
+        let merged = merge(fixture.clone(), fixture.clone());
+
+        assert_eq!(merged, fixture);
+
+        Ok(())
+    }
+
     #[test]
     fn real_world_data() -> Result<()> {
         let input1 = load_daylio_backup("tests/data/old.daylio".as_ref())?;